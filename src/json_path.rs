@@ -1,8 +1,12 @@
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
-use serde_json::Value;
+use serde_json::{Map, Value};
 use thiserror::Error;
-use crate::json_path::path_part::PathPart;
+use crate::json_path::element_key::ElementKey;
+use crate::json_path::path_part::{normalize_index, PathPart};
+use crate::json_path::path_style::PathStyle;
+use crate::json_path::value_matcher::ValueMatcher;
+use crate::syntax::{is_plain_key, split_segments};
 
 #[cfg(feature = "serde")]
 use serde::{Serialize, Serializer, Deserialize, Deserializer};
@@ -11,15 +15,28 @@ use crate::json_path::json_path_visitor::JsonPathVisitor;
 
 
 pub mod path_part;
+pub mod element_key;
+pub mod path_style;
+pub mod value_matcher;
 
 #[cfg(feature = "serde")]
 mod json_path_visitor;
 
-#[derive(Debug, Default, Clone, PartialEq)]
+/// Path to a single value in a document. Paths are ordered part by part, so a path comes before
+/// the paths nested inside it. Keys come before indices and indices before elements, where keys
+/// are compared as strings, indices as numbers and elements by their field and then their value.
+/// This is the order of the values in the document only for paths without negative indices or
+/// elements, and only while objects keep their keys sorted, which they do not when serde_json
+/// is built with `preserve_order`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct JsonPath {
     parts: Vec<PathPart>,
 }
 
+/// The most nulls [JsonPath::insert] pads an array with to reach a new index, so a path like
+/// `$.list.999999999` cannot allocate gigabytes.
+pub const MAX_ARRAY_PADDING: usize = 1024;
+
 #[derive(Debug, Error, PartialEq)]
 pub enum JsonPathResolveError {
     #[error("Failed to resolve part '{0}'")]
@@ -29,7 +46,13 @@ pub enum JsonPathResolveError {
     MissingKey(String),
 
     #[error("Missing index '{0}' on array")]
-    MissingIndex(usize),
+    MissingIndex(isize),
+
+    #[error("Missing element '[{0}]' on array")]
+    MissingElement(ElementKey),
+
+    #[error("Index '{0}' lies more than {MAX_ARRAY_PADDING} elements past the end of the array")]
+    IndexTooFarPastEnd(isize),
 }
 
 impl JsonPath {
@@ -37,6 +60,17 @@ impl JsonPath {
         self.parts.push(part);
     }
 
+    /// Removes the last part of the path and returns it, moving the path to the parent of the
+    /// value it pointed to.
+    pub(crate) fn pop(&mut self) -> Option<PathPart> {
+        self.parts.pop()
+    }
+
+    /// Checks if the path points to the value of the other path or to a value nested inside it.
+    pub(crate) fn starts_with(&self, other: &JsonPath) -> bool {
+        self.parts.starts_with(&other.parts)
+    }
+
     pub fn parent(&self) -> Option<JsonPath> {
         match self.parts.last() {
             Some(_) => {
@@ -49,6 +83,60 @@ impl JsonPath {
         }
     }
 
+    /// Returns the last part of the path, which is the key or index of the value the path points
+    /// to within its parent.
+    pub fn last(&self) -> Option<&PathPart> {
+        self.parts.last()
+    }
+
+    /// Writes the path using the given notation. Both the dot and bracket styles can be parsed
+    /// back into an equal path.
+    pub fn to_string_with_style(&self, style: PathStyle) -> String {
+        let mut output = String::new();
+
+        if style != PathStyle::Human || self.parts.is_empty() {
+            output.push('$');
+        }
+
+        for (i, part) in self.parts.iter().enumerate() {
+            match style {
+                PathStyle::Dot => output.push_str(&part.to_segment_string()),
+                PathStyle::Bracket => output.push_str(&part.to_normalized_string()),
+                PathStyle::Human => match part {
+                    PathPart::Key(key) if is_plain_key(key) && i == 0 => output.push_str(key),
+                    PathPart::Key(key) if is_plain_key(key) => output.push_str(&format!(".{}", key)),
+                    part => output.push_str(&part.to_normalized_string()),
+                },
+            }
+        }
+
+        output
+    }
+
+    /// Walks the whole document and returns the path of every value that matches, in document
+    /// order. The matcher can be a value or string to compare with, a regular expression or a
+    /// closure. The same can be done with a search like `$.**[?@ == "secret"]`, but this does not
+    /// need to parse anything and accepts any Rust predicate.
+    pub fn find_paths(target: &Value, matcher: impl ValueMatcher) -> Vec<JsonPath> {
+        let mut paths = vec![];
+
+        find_paths_inner(target, &matcher, &mut JsonPath::default(), &mut paths);
+
+        paths
+    }
+
+    /// Calls the function with every value the paths point to, walking the document only once.
+    /// The paths should be sorted and without duplicates, and should all start with the prefix,
+    /// which is the path of the target. Values nested inside other values are visited first, so
+    /// replacing a value does not skip the values inside it. Paths that do not resolve are
+    /// skipped. Returns the number of values that were visited.
+    pub(crate) fn for_each_mut<F>(paths: &[JsonPath], prefix: &JsonPath, target: &mut Value, f: &mut F) -> usize
+        where
+            F: FnMut(&JsonPath, &mut Value),
+    {
+        for_each_mut_inner(paths, prefix.parts.len(), target, f)
+    }
+
     pub fn resolve<'a>(&self, value: &'a Value) -> Result<&'a Value, JsonPathResolveError> {
         let mut working_value = value;
 
@@ -62,12 +150,20 @@ impl JsonPath {
                     working_value = value;
                 }
                 (Value::Array(array), PathPart::Index(index)) => {
-                    let Some(value) = array.get(*index) else {
+                    let Some(value) = normalize_index(*index, array.len())
+                        .and_then(|index| array.get(index)) else {
                         return Err(JsonPathResolveError::MissingIndex(*index));
                     };
 
                     working_value = value;
                 },
+                (Value::Array(array), PathPart::Element(key)) => {
+                    let Some(position) = key.position(array) else {
+                        return Err(JsonPathResolveError::MissingElement(key.clone()));
+                    };
+
+                    working_value = &array[position];
+                },
                 _ => {
                     return Err(JsonPathResolveError::FailedToResolvePart(part.clone()));
                 }
@@ -90,12 +186,20 @@ impl JsonPath {
                     working_value = value;
                 }
                 (Value::Array(array), PathPart::Index(index)) => {
-                    let Some(value) = array.get_mut(*index) else {
+                    let Some(value) = normalize_index(*index, array.len())
+                        .and_then(|index| array.get_mut(index)) else {
                         return Err(JsonPathResolveError::MissingIndex(*index));
                     };
 
                     working_value = value;
                 },
+                (Value::Array(array), PathPart::Element(key)) => {
+                    let Some(position) = key.position(array) else {
+                        return Err(JsonPathResolveError::MissingElement(key.clone()));
+                    };
+
+                    working_value = &mut array[position];
+                },
                 _ => {
                     return Err(JsonPathResolveError::FailedToResolvePart(part.clone()));
                 }
@@ -104,6 +208,159 @@ impl JsonPath {
 
         Ok(working_value)
     }
+
+    /// Stores the value at the path, returning the value it replaced. Missing keys and indices
+    /// along the way are created as objects or arrays depending on the part that follows them,
+    /// and arrays are padded with nulls up to a new index, as long as that takes no more than
+    /// [MAX_ARRAY_PADDING] nulls. Elements selected by a field are never created.
+    pub fn insert(&self, target: &mut Value, value: Value) -> Result<Option<Value>, JsonPathResolveError> {
+        let Some((last, parents)) = self.parts.split_last() else {
+            return Ok(Some(std::mem::replace(target, value)));
+        };
+
+        let mut working_value = target;
+
+        for (part, next) in parents.iter().zip(&self.parts[1..]) {
+            let empty = match next {
+                PathPart::Key(_) => Value::Object(Map::new()),
+                PathPart::Index(_) | PathPart::Element(_) => Value::Array(vec![]),
+            };
+
+            working_value = child_or_insert(working_value, part, empty)?;
+        }
+
+        match (working_value, last) {
+            (Value::Object(object), PathPart::Key(key)) => Ok(object.insert(key.clone(), value)),
+            (Value::Array(array), PathPart::Index(index)) if *index >= 0 && *index as usize >= array.len() => {
+                pad_and_push(array, *index, value)?;
+
+                Ok(None)
+            }
+            (working_value, part) => child_or_insert(working_value, part, Value::Null)
+                .map(|slot| Some(std::mem::replace(slot, value))),
+        }
+    }
+
+    /// Removes the value at the path from its parent and returns it. Later elements of an array
+    /// move up to fill the gap. Removing the root takes the whole value and leaves null behind.
+    pub fn remove(&self, target: &mut Value) -> Result<Value, JsonPathResolveError> {
+        let (Some(mut parent), Some(last)) = (self.parent(), self.last()) else {
+            return Ok(target.take());
+        };
+
+        match (parent.resolve_mut(target)?, last) {
+            (Value::Object(object), PathPart::Key(key)) => object.remove(key)
+                .ok_or_else(|| JsonPathResolveError::MissingKey(key.to_string())),
+            (Value::Array(array), PathPart::Index(index)) => normalize_index(*index, array.len())
+                .map(|index| array.remove(index))
+                .ok_or(JsonPathResolveError::MissingIndex(*index)),
+            (Value::Array(array), PathPart::Element(key)) => key.position(array)
+                .map(|position| array.remove(position))
+                .ok_or_else(|| JsonPathResolveError::MissingElement(key.clone())),
+            (_, part) => Err(JsonPathResolveError::FailedToResolvePart(part.clone())),
+        }
+    }
+}
+
+/// Returns the child stored under the part, storing the default value first when it is missing.
+fn child_or_insert<'a>(value: &'a mut Value, part: &PathPart, default: Value) -> Result<&'a mut Value, JsonPathResolveError> {
+    match (value, part) {
+        (Value::Object(object), PathPart::Key(key)) => Ok(object.entry(key.clone()).or_insert(default)),
+        (Value::Array(array), PathPart::Index(index)) => {
+            if *index >= 0 && *index as usize >= array.len() {
+                pad_and_push(array, *index, default)?;
+            }
+
+            normalize_index(*index, array.len())
+                .and_then(|index| array.get_mut(index))
+                .ok_or(JsonPathResolveError::MissingIndex(*index))
+        }
+        (Value::Array(array), PathPart::Element(key)) => key.position(array)
+            .map(|position| &mut array[position])
+            .ok_or_else(|| JsonPathResolveError::MissingElement(key.clone())),
+        (_, part) => Err(JsonPathResolveError::FailedToResolvePart(part.clone())),
+    }
+}
+
+/// Pads the array with nulls up to the index and stores the value there, unless that takes more
+/// than [MAX_ARRAY_PADDING] nulls.
+fn pad_and_push(array: &mut Vec<Value>, index: isize, value: Value) -> Result<(), JsonPathResolveError> {
+    if index as usize - array.len() > MAX_ARRAY_PADDING {
+        return Err(JsonPathResolveError::IndexTooFarPastEnd(index));
+    }
+
+    array.resize(index as usize, Value::Null);
+    array.push(value);
+
+    Ok(())
+}
+
+/// Visits the values of paths that all share the same first parts up to the depth, where the
+/// value at that depth is the target.
+fn for_each_mut_inner<F>(paths: &[JsonPath], depth: usize, target: &mut Value, f: &mut F) -> usize
+    where
+        F: FnMut(&JsonPath, &mut Value),
+{
+    let (current, mut nested) = match paths.split_first() {
+        Some((path, nested)) if path.parts.len() == depth => (Some(path), nested),
+        _ => (None, paths),
+    };
+
+    let mut visited = 0;
+
+    while let Some(first) = nested.first() {
+        let part = &first.parts[depth];
+        let end = nested.iter()
+            .position(|path| path.parts[depth] != *part)
+            .unwrap_or(nested.len());
+
+        let (group, rest) = nested.split_at(end);
+        nested = rest;
+
+        let child = match (&mut *target, part) {
+            (Value::Object(object), PathPart::Key(key)) => object.get_mut(key),
+            (Value::Array(array), PathPart::Index(index)) => normalize_index(*index, array.len())
+                .and_then(|index| array.get_mut(index)),
+            (Value::Array(array), PathPart::Element(key)) => key.position(array)
+                .map(|position| &mut array[position]),
+            _ => None,
+        };
+
+        if let Some(child) = child {
+            visited += for_each_mut_inner(group, depth + 1, child, f);
+        }
+    }
+
+    if let Some(path) = current {
+        f(path, target);
+        visited += 1;
+    }
+
+    visited
+}
+
+fn find_paths_inner(value: &Value, matcher: &impl ValueMatcher, path: &mut JsonPath, paths: &mut Vec<JsonPath>) {
+    if matcher.matches(value) {
+        paths.push(path.clone());
+    }
+
+    match value {
+        Value::Array(array) => {
+            for (i, value) in array.iter().enumerate() {
+                path.push(PathPart::Index(i as isize));
+                find_paths_inner(value, matcher, path, paths);
+                path.parts.pop();
+            }
+        }
+        Value::Object(object) => {
+            for (key, value) in object {
+                path.push(PathPart::Key(key.to_string()));
+                find_paths_inner(value, matcher, path, paths);
+                path.parts.pop();
+            }
+        }
+        _ => {}
+    }
 }
 
 #[derive(Debug, Error, PartialEq)]
@@ -113,13 +370,16 @@ pub enum JsonPathParseError {
 
     #[error("JSON path string should start with a '$', but got '{0}'")]
     IncorrectRoot(String),
+
+    #[error("Invalid path part '{0}', expected a quoted key or an index between brackets")]
+    InvalidPart(String),
 }
 
 impl FromStr for JsonPath {
     type Err = JsonPathParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split('.');
+        let mut parts = split_segments(s).into_iter();
 
         match parts.next() {
             Some("$") => Ok(()),
@@ -129,8 +389,8 @@ impl FromStr for JsonPath {
 
         Ok(JsonPath {
             parts: parts
-                .map(|part| PathPart::from(part.to_string()))
-                .collect(),
+                .map(PathPart::from_str)
+                .collect::<Result<_, _>>()?,
         })
     }
 }
@@ -151,7 +411,7 @@ impl Display for JsonPath {
         write!(f, "$")?;
 
         for part in &self.parts {
-            write!(f, "{}", part)?;
+            write!(f, "{}", part.to_segment_string())?;
         }
 
         Ok(())
@@ -181,9 +441,45 @@ impl<'de> Deserialize<'de> for JsonPath {
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
-    use serde_json::json;
-    use crate::json_path::{JsonPath, JsonPathParseError};
+    use quickcheck::{quickcheck, Arbitrary, Gen};
+    use regex::Regex;
+    use serde_json::{json, Value};
+    use crate::json_path::{JsonPath, JsonPathParseError, JsonPathResolveError};
+    use crate::json_path::element_key::ElementKey;
     use crate::json_path::path_part::PathPart;
+    use crate::json_path::path_style::PathStyle;
+    use crate::json_search::JsonSearch;
+
+    impl Arbitrary for PathPart {
+        fn arbitrary(g: &mut Gen) -> Self {
+            // Keys are mostly built from characters that have a meaning in the path syntax, with
+            // the occasional fully random string.
+            let tricky = ['a', 'b', '0', '1', '-', '_', '.', '*', '?', ',', ':', '=', ' ', '[', ']', '"', '\'', '\\', '\n', '\u{1}', 'é', '😀'];
+
+            let tricky_key = |g: &mut Gen| -> String {
+                let length = usize::arbitrary(g) % 6;
+                (0..length).map(|_| *g.choose(&tricky).unwrap()).collect()
+            };
+
+            match u8::arbitrary(g) % 5 {
+                0 => PathPart::Index(isize::arbitrary(g)),
+                1 => PathPart::Key(String::arbitrary(g)),
+                2 => {
+                    let values = [json!(i64::arbitrary(g)), json!(tricky_key(g)), json!(bool::arbitrary(g)), Value::Null];
+                    PathPart::Element(ElementKey::new(tricky_key(g), g.choose(&values).unwrap().clone()))
+                }
+                _ => PathPart::Key(tricky_key(g)),
+            }
+        }
+    }
+
+    impl Arbitrary for JsonPath {
+        fn arbitrary(g: &mut Gen) -> Self {
+            JsonPath {
+                parts: Vec::arbitrary(g),
+            }
+        }
+    }
 
     #[test]
     fn correctly_formatted_json_paths_strings_are_parsed_correctly() {
@@ -208,11 +504,85 @@ mod tests {
         });
     }
 
+    #[test]
+    fn bracket_notation_is_parsed_correctly() {
+        assert_eq!(JsonPath::from_str(r#"$["a.b"]"#).unwrap(), JsonPath {
+            parts: vec![PathPart::Key("a.b".to_string())],
+        });
+
+        assert_eq!(JsonPath::from_str("$['0'][0]").unwrap(), JsonPath {
+            parts: vec![PathPart::Key("0".to_string()), PathPart::Index(0)],
+        });
+
+        assert_eq!(JsonPath::from_str(r#"$.a["b\"]\n"].c"#).unwrap(), JsonPath {
+            parts: vec![PathPart::Key("a".to_string()), PathPart::Key("b\"]\n".to_string()), PathPart::Key("c".to_string())],
+        });
+
+        assert_eq!(JsonPath::from_str(r#"$.a.["*"]"#).unwrap(), JsonPath {
+            parts: vec![PathPart::Key("a".to_string()), PathPart::Key("*".to_string())],
+        });
+    }
+
+    #[test]
+    fn paths_are_displayed_in_a_format_that_can_be_parsed_again() {
+        let path = JsonPath {
+            parts: vec![
+                PathPart::Key("a".to_string()),
+                PathPart::Index(3),
+                PathPart::Key("a.b".to_string()),
+                PathPart::Key("0".to_string()),
+                PathPart::Key("".to_string()),
+                PathPart::Key("quote\"d".to_string()),
+            ],
+        };
+
+        assert_eq!(path.to_string(), r#"$.a.3["a.b"]["0"][""]["quote\"d"]"#);
+        assert_eq!(JsonPath::from_str(&path.to_string()), Ok(path));
+    }
+
+    #[test]
+    fn paths_round_trip_through_every_parseable_style() {
+        fn round_trips(path: JsonPath) -> bool {
+            [PathStyle::Dot, PathStyle::Bracket].into_iter()
+                .all(|style| JsonPath::from_str(&path.to_string_with_style(style)) == Ok(path.clone()))
+        }
+
+        quickcheck(round_trips as fn(JsonPath) -> bool);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn paths_round_trip_through_serde() {
+        fn round_trips(path: JsonPath) -> bool {
+            let serialized = serde_json::to_string(&path).unwrap();
+            serde_json::from_str::<JsonPath>(&serialized).ok() == Some(path)
+        }
+
+        quickcheck(round_trips as fn(JsonPath) -> bool);
+    }
+
+    #[test]
+    fn paths_are_written_in_the_requested_style() {
+        let path = JsonPath::from_str(r#"$.users.3.name["a.b"]"#).unwrap();
+
+        assert_eq!(path.to_string_with_style(PathStyle::Dot), r#"$.users.3.name["a.b"]"#);
+        assert_eq!(path.to_string_with_style(PathStyle::Bracket), "$['users'][3]['name']['a.b']");
+        assert_eq!(path.to_string_with_style(PathStyle::Human), r#"users[3].name['a.b']"#);
+
+        assert_eq!(JsonPath::default().to_string_with_style(PathStyle::Human), "$");
+
+        let escaped = JsonPath { parts: vec![PathPart::Key("it's\n\u{1}".to_string())] };
+        assert_eq!(escaped.to_string_with_style(PathStyle::Bracket), r"$['it\'s\n\u0001']");
+        assert_eq!(escaped.to_string_with_style(PathStyle::Human), r"['it\'s\n\u0001']");
+    }
+
     #[test]
     fn incorrectly_formatted_json_paths_strings_return_errors() {
         assert_eq!(JsonPath::from_str(""), Err(JsonPathParseError::IncorrectRoot("".to_string())));
         assert_eq!(JsonPath::from_str("?"), Err(JsonPathParseError::IncorrectRoot("?".to_string())));
         assert_eq!(JsonPath::from_str("!"), Err(JsonPathParseError::IncorrectRoot("!".to_string())));
+        assert_eq!(JsonPath::from_str("$[a]"), Err(JsonPathParseError::InvalidPart("[a]".to_string())));
+        assert_eq!(JsonPath::from_str("$['a]"), Err(JsonPathParseError::InvalidPart("['a]".to_string())));
     }
 
     #[test]
@@ -231,10 +601,129 @@ mod tests {
         assert_eq!(JsonPath::from(["a", "0"]).resolve(&json!({ "a": [10] })), Ok(&json!(10)));
     }
 
+    #[test]
+    fn negative_indices_are_resolved_from_the_end() {
+        assert_eq!(JsonPath::from_str("$.events.-1").unwrap(), JsonPath {
+            parts: vec![PathPart::Key("events".to_string()), PathPart::Index(-1)],
+        });
+
+        assert_eq!(JsonPath::from(["a", "-1"]).resolve(&json!({ "a": [10, 20] })), Ok(&json!(20)));
+        assert_eq!(JsonPath::from(["a", "-2"]).resolve(&json!({ "a": [10, 20] })), Ok(&json!(10)));
+        assert_eq!(JsonPath::from(["a", "-1"]).resolve_mut(&mut json!({ "a": [10, 20] })), Ok(&mut json!(20)));
+        assert_eq!(JsonPath::from(["a", "-3"]).resolve(&json!({ "a": [10, 20] })), Err(JsonPathResolveError::MissingIndex(-3)));
+        assert_eq!(JsonPath::from(["a", "-1"]).resolve_mut(&mut json!({ "a": [] })), Err(JsonPathResolveError::MissingIndex(-1)));
+    }
+
     #[test]
     fn mut_paths_are_resolved_correctly() {
         assert_eq!(JsonPath::default().resolve_mut(&mut json!({ "a": 10 })), Ok(&mut json!({ "a": 10 })));
         assert_eq!(JsonPath::from(["a"]).resolve_mut(&mut json!({ "a": 10 })), Ok(&mut json!(10)));
         assert_eq!(JsonPath::from(["a", "0"]).resolve_mut(&mut json!({ "a": [10] })), Ok(&mut json!(10)));
     }
+
+    #[test]
+    fn paths_are_found_by_their_value() {
+        let target = json!({
+            "database": { "password": "secret", "port": 5432 },
+            "replicas": [{ "password": "secret" }, { "password": "s3cret" }],
+            "token": "not-a-secret",
+        });
+
+        assert_eq!(JsonPath::find_paths(&target, "secret"), vec![
+            JsonPath::from(["database", "password"]),
+            JsonPath::from(["replicas", "0", "password"]),
+        ]);
+
+        assert_eq!(JsonPath::find_paths(&target, Regex::new("^s.cret$").unwrap()), vec![
+            JsonPath::from(["database", "password"]),
+            JsonPath::from(["replicas", "0", "password"]),
+            JsonPath::from(["replicas", "1", "password"]),
+        ]);
+
+        assert_eq!(JsonSearch::from_str(r#"$.**[?@ == "secret"]"#).unwrap().resolve(&target), Ok(JsonPath::find_paths(&target, "secret")));
+        assert_eq!(JsonPath::find_paths(&target, json!(5432)), vec![JsonPath::from(["database", "port"])]);
+        assert_eq!(JsonPath::find_paths(&target, json!({ "password": "s3cret" })), vec![JsonPath::from(["replicas", "1"])]);
+        assert_eq!(JsonPath::find_paths(&target, |value: &Value| value.is_object()), vec![
+            JsonPath::default(),
+            JsonPath::from(["database"]),
+            JsonPath::from(["replicas", "0"]),
+            JsonPath::from(["replicas", "1"]),
+        ]);
+    }
+
+    #[test]
+    fn values_are_inserted_and_removed() {
+        let mut target = json!({ "a": { "b": 1 }, "list": [1, 2, 3] });
+
+        assert_eq!(JsonPath::from(["a", "b"]).insert(&mut target, json!(2)), Ok(Some(json!(1))));
+        assert_eq!(JsonPath::from(["x", "y", "2"]).insert(&mut target, json!(true)), Ok(None));
+        assert_eq!(JsonPath::from(["list", "-1"]).insert(&mut target, json!(4)), Ok(Some(json!(3))));
+        assert_eq!(JsonPath::from(["list", "-5"]).insert(&mut target, json!(4)), Err(JsonPathResolveError::MissingIndex(-5)));
+        assert_eq!(JsonPath::from(["a", "b", "c"]).insert(&mut target, json!(4)), Err(JsonPathResolveError::FailedToResolvePart(PathPart::Key("c".to_string()))));
+        assert_eq!(JsonPath::from(["list", "999999999"]).insert(&mut target, json!(4)), Err(JsonPathResolveError::IndexTooFarPastEnd(999999999)));
+        assert_eq!(target, json!({ "a": { "b": 2 }, "list": [1, 2, 4], "x": { "y": [null, null, true] } }));
+
+        assert_eq!(JsonPath::from(["list", "0"]).remove(&mut target), Ok(json!(1)));
+        assert_eq!(JsonPath::from(["a"]).remove(&mut target), Ok(json!({ "b": 2 })));
+        assert_eq!(JsonPath::from(["a"]).remove(&mut target), Err(JsonPathResolveError::MissingKey("a".to_string())));
+        assert_eq!(target, json!({ "list": [2, 4], "x": { "y": [null, null, true] } }));
+
+        assert_eq!(JsonPath::default().remove(&mut target), Ok(json!({ "list": [2, 4], "x": { "y": [null, null, true] } })));
+        assert_eq!(target, Value::Null);
+
+        let mut padded = json!([]);
+
+        assert_eq!(JsonPath::from(["1025", "a"]).insert(&mut padded, json!(1)), Err(JsonPathResolveError::IndexTooFarPastEnd(1025)));
+        assert_eq!(JsonPath::from(["1024", "a"]).insert(&mut padded, json!(1)), Ok(None));
+        assert_eq!(padded.as_array().map(Vec::len), Some(1025));
+    }
+
+    #[test]
+    fn paths_are_ordered_part_by_part() {
+        let mut paths: Vec<JsonPath> = ["$.b", "$.a.b", "$.list[1]", "$.list[-1]", "$.list[id=1]", "$.list", "$.list[0].a", "$[0]"]
+            .into_iter()
+            .map(|s| JsonPath::from_str(s).unwrap())
+            .collect();
+
+        paths.sort();
+
+        assert_eq!(paths.iter().map(JsonPath::to_string).collect::<Vec<_>>(), vec![
+            "$.a.b", "$.b", "$.list", "$.list.-1", "$.list.0.a", "$.list.1", "$.list[id=1]", "$.0",
+        ]);
+    }
+
+    #[test]
+    fn array_elements_are_resolved_by_their_fields() {
+        let mut target = json!({
+            "users": [
+                { "id": 7, "email": "ann@example.test" },
+                { "id": 42, "email": "bob@example.test", "display name": "Bob" },
+            ],
+        });
+
+        let path = JsonPath::from_str("$.users.[id=42].email").unwrap();
+        assert_eq!(path, JsonPath {
+            parts: vec![
+                PathPart::Key("users".to_string()),
+                PathPart::Element(ElementKey::new("id", 42)),
+                PathPart::Key("email".to_string()),
+            ],
+        });
+
+        assert_eq!(path.to_string(), "$.users[id=42].email");
+        assert_eq!(path.resolve(&target), Ok(&json!("bob@example.test")));
+        assert_eq!(JsonPath::from_str("$.users[id=42.0].email").unwrap().resolve(&target), Ok(&json!("bob@example.test")));
+        assert_eq!(JsonPath::from_str(r#"$.users["display name"='Bob'].id"#).unwrap().resolve(&target), Ok(&json!(42)));
+        assert_eq!(JsonPath::from_str("$.users[id=8]").unwrap().resolve(&target), Err(JsonPathResolveError::MissingElement(ElementKey::new("id", 8))));
+        assert_eq!(JsonPath::from_str("$.users[id=abc]"), Err(JsonPathParseError::InvalidPart("[id=abc]".to_string())));
+        assert_eq!(JsonPath::from_str("$.users[id=[1]]"), Err(JsonPathParseError::InvalidPart("[id=[1]]".to_string())));
+
+        target["users"].as_array_mut().unwrap().reverse();
+        assert_eq!(path.resolve(&target), Ok(&json!("bob@example.test")));
+
+        assert_eq!(path.insert(&mut target, json!("robert@example.test")), Ok(Some(json!("bob@example.test"))));
+        assert_eq!(JsonPath::from_str("$.users[id=7]").unwrap().remove(&mut target), Ok(json!({ "id": 7, "email": "ann@example.test" })));
+        assert_eq!(JsonPath::from_str("$.users[id=7].email").unwrap().insert(&mut target, json!("x")), Err(JsonPathResolveError::MissingElement(ElementKey::new("id", 7))));
+        assert_eq!(target["users"], json!([{ "id": 42, "email": "robert@example.test", "display name": "Bob" }]));
+    }
 }
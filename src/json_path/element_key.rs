@@ -0,0 +1,109 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde_json::Value;
+use crate::json_path::JsonPathParseError;
+use crate::syntax::{is_plain_key, parse_quoted, parse_string_literal, quote};
+
+/// Identifies an element of an array by the value of one of its fields rather than by its
+/// position, written as `[id=42]` or `["display name"='Ann']`. Unlike an index, it keeps pointing
+/// at the same element when the array is sorted or extended. The value is a number, string,
+/// boolean or null, and numbers are compared by their numeric value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementKey {
+    field: String,
+    value: Value,
+}
+
+impl ElementKey {
+    pub fn new(field: impl Into<String>, value: impl Into<Value>) -> Self {
+        ElementKey {
+            field: field.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Checks if the element is an object whose field holds the value of this key.
+    pub fn matches(&self, element: &Value) -> bool {
+        match (element.get(&self.field), &self.value) {
+            (Some(Value::Number(left)), Value::Number(right)) => left.as_f64() == right.as_f64(),
+            (Some(value), expected) => value == expected,
+            (None, _) => false,
+        }
+    }
+
+    /// Returns the position of the first element in the array that matches.
+    pub fn position(&self, array: &[Value]) -> Option<usize> {
+        array.iter().position(|element| self.matches(element))
+    }
+}
+
+/// Orders keys by their field and then by how their value is written, which keeps them usable
+/// in ordered paths even though JSON values themselves have no order.
+impl PartialOrd for ElementKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ElementKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.field.cmp(&other.field)
+            .then_with(|| self.value.to_string().cmp(&other.value.to_string()))
+    }
+}
+
+impl FromStr for ElementKey {
+    type Err = JsonPathParseError;
+
+    /// Parses the contents of the brackets, like `id=42` for `[id=42]`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || JsonPathParseError::InvalidPart(format!("[{}]", s));
+
+        let (field, value) = match parse_string_literal(s.trim_start()) {
+            Some((field, length)) => {
+                let rest = s.trim_start()[length..].trim_start();
+                (field, rest.strip_prefix('=').ok_or_else(invalid)?)
+            }
+            None => {
+                let (field, value) = s.split_once('=').ok_or_else(invalid)?;
+
+                if !is_plain_key(field.trim()) {
+                    return Err(invalid());
+                }
+
+                (field.trim().to_string(), value)
+            }
+        };
+
+        let value = match parse_quoted(value.trim()) {
+            Some(string) => Value::String(string),
+            None => match serde_json::from_str(value.trim()) {
+                Ok(value @ (Value::Null | Value::Bool(_) | Value::Number(_))) => value,
+                _ => return Err(invalid()),
+            },
+        };
+
+        Ok(ElementKey {
+            field,
+            value,
+        })
+    }
+}
+
+impl Display for ElementKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match is_plain_key(&self.field) {
+            true => write!(f, "{}={}", self.field, self.value),
+            false => write!(f, "{}={}", quote(&self.field), self.value),
+        }
+    }
+}
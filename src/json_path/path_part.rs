@@ -1,9 +1,62 @@
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::json_path::element_key::ElementKey;
+use crate::json_path::JsonPathParseError;
+use crate::syntax::{is_plain_key, parse_quoted, quote, quote_normalized};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PathPart {
     Key(String),
-    Index(usize),
+    Index(isize),
+
+    /// The first element of an array with a field holding a certain value, written as `[id=42]`.
+    Element(ElementKey),
+}
+
+impl PathPart {
+    /// Returns the part as a segment of a path, using bracket notation for keys that cannot be
+    /// written as is.
+    pub(crate) fn to_segment_string(&self) -> String {
+        match self {
+            PathPart::Key(key) if is_plain_key(key) => format!(".{}", key),
+            PathPart::Key(key) => format!("[{}]", quote(key)),
+            PathPart::Index(index) => format!(".{}", index),
+            PathPart::Element(key) => format!("[{}]", key),
+        }
+    }
+
+    /// Returns the part as a bracketed segment of an RFC 9535 normalized path.
+    pub(crate) fn to_normalized_string(&self) -> String {
+        match self {
+            PathPart::Key(key) => format!("[{}]", quote_normalized(key)),
+            PathPart::Index(index) => format!("[{}]", index),
+            PathPart::Element(key) => format!("[{}]", key),
+        }
+    }
+}
+
+impl FromStr for PathPart {
+    type Err = JsonPathParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some(inner) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) else {
+            return Ok(PathPart::from(s.to_string()));
+        };
+
+        let inner = inner.trim();
+
+        if let Some(key) = parse_quoted(inner) {
+            return Ok(PathPart::Key(key));
+        }
+
+        if inner.contains('=') {
+            return Ok(PathPart::Element(inner.parse()?));
+        }
+
+        inner.parse()
+            .map(PathPart::Index)
+            .map_err(|_| JsonPathParseError::InvalidPart(s.to_string()))
+    }
 }
 
 impl From<String> for PathPart {
@@ -21,8 +74,21 @@ impl Display for PathPart {
         let string = match self {
             PathPart::Key(value) => value.to_string(),
             PathPart::Index(value) => value.to_string(),
+            PathPart::Element(key) => key.to_string(),
         };
 
         write!(f, "{}", string)
     }
 }
+
+/// Converts a possibly negative index into a position in an array of the given length, where
+/// negative indices count back from the end of the array.
+pub(crate) fn normalize_index(index: isize, len: usize) -> Option<usize> {
+    let normalized = if index < 0 {
+        len.checked_sub(index.unsigned_abs())?
+    } else {
+        index as usize
+    };
+
+    (normalized < len).then_some(normalized)
+}
@@ -0,0 +1,15 @@
+/// Notation used when writing a [JsonPath](crate::json_path::JsonPath) as a string.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PathStyle {
+    /// Dot separated segments like `$.users.3.name`, with keys that cannot be written as is put
+    /// between quotes and brackets. This is the canonical form used by `Display` and `Serialize`.
+    #[default]
+    Dot,
+
+    /// Every segment between brackets like `$['users'][3]['name']`, matching the normalized paths
+    /// of RFC 9535.
+    Bracket,
+
+    /// A form meant for people rather than parsers like `users[3].name`, without the root.
+    Human,
+}
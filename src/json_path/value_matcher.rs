@@ -0,0 +1,39 @@
+use regex::Regex;
+use serde_json::Value;
+
+/// Something a value can be compared against when looking up where it is stored, see
+/// [JsonPath::find_paths](crate::json_path::JsonPath::find_paths).
+pub trait ValueMatcher {
+    fn matches(&self, value: &Value) -> bool;
+}
+
+/// Matches values that are equal to this value.
+impl ValueMatcher for Value {
+    fn matches(&self, value: &Value) -> bool {
+        self == value
+    }
+}
+
+/// Matches strings that are equal to this string.
+impl ValueMatcher for &str {
+    fn matches(&self, value: &Value) -> bool {
+        value.as_str() == Some(*self)
+    }
+}
+
+/// Matches strings that contain a match of the regular expression. Other values never match.
+impl ValueMatcher for Regex {
+    fn matches(&self, value: &Value) -> bool {
+        value.as_str().is_some_and(|string| self.is_match(string))
+    }
+}
+
+/// Matches values for which the closure returns true.
+impl<F> ValueMatcher for F
+    where
+        F: Fn(&Value) -> bool,
+{
+    fn matches(&self, value: &Value) -> bool {
+        self(value)
+    }
+}
@@ -2,9 +2,22 @@ use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 use serde_json::Value;
 use thiserror::Error;
-use crate::json_path::JsonPath;
-use crate::json_path::path_part::PathPart;
-use crate::json_search::search_part::SearchPart;
+use crate::json_path::{JsonPath, JsonPathResolveError};
+use crate::json_path::element_key::ElementKey;
+use crate::json_path::path_part::{normalize_index, PathPart};
+use crate::json_search::array_slice::ArraySlice;
+use crate::json_search::depth_range::DepthRange;
+use crate::json_search::edit_walk::EditWalk;
+use crate::json_search::expansion::{ChildFilter, Cursor, Expansion};
+use crate::json_search::filter::FilterContext;
+use crate::json_search::filter_function::FunctionType;
+use crate::json_search::function_registry::FunctionRegistry;
+use crate::json_search::resolve_iter::ResolveIter;
+use crate::json_search::search_match::SearchMatch;
+use crate::json_search::search_part::{parse_segments, SearchPart};
+use crate::json_search::search_syntax::SearchSyntax;
+use crate::json_search::value_type::ValueType;
+use crate::syntax::{split_segments, split_stages};
 
 #[cfg(feature = "serde")]
 use serde::{Serialize, Serializer, Deserialize, Deserializer};
@@ -12,6 +25,21 @@ use serde::{Serialize, Serializer, Deserialize, Deserializer};
 use crate::json_search::json_search_visitor::JsonSearchVisitor;
 
 pub mod search_part;
+pub mod array_slice;
+pub mod depth_range;
+pub mod filter;
+pub mod filter_function;
+pub mod function_registry;
+pub mod key_pattern;
+pub mod value_type;
+pub mod search_syntax;
+pub mod search_match;
+pub mod resolve_iter;
+pub mod path_template;
+pub mod rewrite_rule;
+mod rfc9535;
+mod expansion;
+mod edit_walk;
 
 #[cfg(feature = "serde")]
 mod json_search_visitor;
@@ -20,6 +48,11 @@ mod json_search_visitor;
 pub struct JsonSearch {
     parts: Vec<SearchPart>,
     optional: bool,
+
+    /// Whether parts that do not match the type of the value they are applied to select nothing
+    /// instead of returning an error, like in RFC 9535. Lenient searches are always optional and
+    /// are written with a `~` root instead of `?`.
+    lenient: bool,
 }
 
 #[derive(Debug, Error, PartialEq)]
@@ -37,7 +70,32 @@ pub enum JsonSearchResolveError {
     MissingRequiredKey(JsonPath, String),
 
     #[error("Missing required index '{1}' at '{0}'")]
-    MissingRequiredIndex(JsonPath, usize),
+    MissingRequiredIndex(JsonPath, isize),
+
+    #[error("Expected a parent at '{0}'")]
+    MissingParent(JsonPath),
+
+    #[error("Missing required element '[{1}]' at '{0}'")]
+    MissingRequiredElement(JsonPath, ElementKey),
+
+    /// The parent of a match could not be found again, which means the paths of matches no
+    /// longer point into the document the search started at.
+    #[error("Failed to resolve the parent '{0}': {1}")]
+    FailedToResolveParent(JsonPath, JsonPathResolveError),
+
+    #[error("Function '{0}' is not registered with the signature it was parsed with")]
+    UnknownFunction(String),
+
+    #[error("Function '{0}' should return a {1:?}, but returned a {2:?}")]
+    InvalidFunctionResult(String, FunctionType, FunctionType),
+}
+
+impl JsonSearchResolveError {
+    /// Checks if the error comes from calling a user-defined function. These errors say nothing
+    /// about the document, so they are returned even by parts that skip other failures.
+    pub(crate) fn is_function_error(&self) -> bool {
+        matches!(self, JsonSearchResolveError::UnknownFunction(_) | JsonSearchResolveError::InvalidFunctionResult(_, _, _))
+    }
 }
 
 impl JsonSearch {
@@ -48,6 +106,7 @@ impl JsonSearch {
                 .map(|value| value.to_string().into())
                 .collect(),
             optional: false,
+            lenient: false,
         }
     }
 
@@ -57,138 +116,605 @@ impl JsonSearch {
         new
     }
 
-    pub fn resolve(&self, target: &Value) -> Result<Vec<JsonPath>, JsonSearchResolveError> {
-        self.resolve_inner(&self.parts, target, JsonPath::default())
+    /// Parses a search using the given syntax. Parsing with [SearchSyntax::Native] is the same as
+    /// using `FromStr`.
+    pub fn parse(s: &str, syntax: SearchSyntax) -> Result<Self, JsonSearchParseError> {
+        JsonSearch::parse_with_functions(s, syntax, &FunctionRegistry::default())
     }
 
-    fn resolve_inner(&self, parts: &[SearchPart], target: &Value, parent: JsonPath) -> Result<Vec<JsonPath>, JsonSearchResolveError> {
-        let mut results = vec![];
-        let remaining = if !parts.is_empty() {
-            &parts[1..]
-        } else {
-            &parts[0..]
+    /// Parses a search whose filters may call the functions in the given registry. Calls to
+    /// unknown functions and calls that do not match the signature of the function return an
+    /// error.
+    pub fn parse_with_functions(s: &str, syntax: SearchSyntax, functions: &FunctionRegistry) -> Result<Self, JsonSearchParseError> {
+        if syntax == SearchSyntax::Rfc9535 {
+            return rfc9535::parse(s, functions);
+        }
+
+        let mut stages = split_stages(s).into_iter();
+        let first = stages.next().unwrap_or_default();
+        let first = if stages.len() > 0 { first.trim() } else { first };
+
+        let (root, parts) = parse_segments(split_segments(first), functions)?;
+
+        let (optional, lenient) = match root {
+            "~" => Ok((true, true)),
+            "?" => Ok((true, false)),
+            "$" => Ok((false, false)),
+            value => Err(JsonSearchParseError::IncorrectRoot(value.to_string())),
+        }?;
+
+        let mut search = JsonSearch {
+            parts,
+            optional,
+            lenient,
         };
 
-        if parts.is_empty() {
-            return Ok(vec![parent]);
+        for stage in stages {
+            let (root, parts) = parse_segments(split_segments(stage.trim()), functions)?;
+
+            if root != "@" {
+                return Err(JsonSearchParseError::IncorrectStageRoot(root.to_string()));
+            }
+
+            search = search.then(&JsonSearch {
+                parts,
+                optional: false,
+                lenient: false,
+            });
         }
 
+        Ok(search)
+    }
 
-        if let Some(part) = parts.get(0) {
-            let resolved = match part {
-                SearchPart::Key(key) => self.resolve_key(remaining, target, parent, key)?,
-                SearchPart::Index(index) => self.resolve_index(remaining, target, parent, index)?,
-                SearchPart::Wildcard => self.resolve_wildcard(remaining, target, parent)?,
+    /// Chains another search that is resolved relative to every match of this one, which is the
+    /// same as a pipe like `$.orders.* | @.lines.*`. Parts keep behaving as they would in their
+    /// own search, so the parts of an optional search stay optional when it is chained with a
+    /// required one. Mismatched types are only skipped when both searches skip them.
+    pub fn then(&self, next: &JsonSearch) -> JsonSearch {
+        let lenient = self.lenient && next.lenient;
+
+        if self.optional == next.optional {
+            return JsonSearch {
+                parts: [self.parts.as_slice(), next.parts.as_slice()].concat(),
+                optional: self.optional,
+                lenient,
             };
+        }
+
+        let stage = |search: &JsonSearch| search.parts.iter()
+            .map(|part| match part {
+                SearchPart::Optional(_) | SearchPart::TypeGuard(_) => part.clone(),
+                part if search.optional => SearchPart::Optional(Box::new(part.clone())),
+                part => part.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        JsonSearch {
+            parts: [stage(self), stage(next)].concat(),
+            optional: false,
+            lenient,
+        }
+    }
+
+    /// Returns the names of the captures in the search, in the order they appear.
+    pub fn captures(&self) -> Vec<&str> {
+        let mut names = vec![];
+
+        for part in &self.parts {
+            part.collect_captures(&mut names);
+        }
+
+        names
+    }
+
+    pub fn resolve(&self, target: &Value) -> Result<Vec<JsonPath>, JsonSearchResolveError> {
+        self.resolve_with_functions(target, &FunctionRegistry::default())
+    }
+
+    /// Resolves the search, returning the key or index every match is stored under instead of
+    /// its full path, like the `~` operator of JSONPath-Plus. A match at the start of the search
+    /// is not stored under anything and is left out.
+    pub fn resolve_keys(&self, target: &Value) -> Result<Vec<PathPart>, JsonSearchResolveError> {
+        let keys = self.resolve(target)?
+            .iter()
+            .filter_map(|path| path.last().cloned())
+            .collect();
+
+        Ok(keys)
+    }
+
+    /// Resolves the search, calling the implementations in the given registry for the
+    /// user-defined functions in filters. This should be the registry the search was parsed with,
+    /// as calls to functions that are missing from it return
+    /// [JsonSearchResolveError::UnknownFunction].
+    pub fn resolve_with_functions(&self, target: &Value, functions: &FunctionRegistry) -> Result<Vec<JsonPath>, JsonSearchResolveError> {
+        let paths = self.resolve_matches_with_functions(target, functions)?
+            .into_iter()
+            .map(SearchMatch::into_path)
+            .collect();
+
+        Ok(paths)
+    }
+
+    /// Resolves the search, returning every match together with the keys and indices bound by
+    /// the captures in the search, like `tenant` in `$.tenants.{tenant}.users.{user}.email`.
+    pub fn resolve_matches(&self, target: &Value) -> Result<Vec<SearchMatch>, JsonSearchResolveError> {
+        self.resolve_matches_with_functions(target, &FunctionRegistry::default())
+    }
+
+    pub fn resolve_matches_with_functions(&self, target: &Value, functions: &FunctionRegistry) -> Result<Vec<SearchMatch>, JsonSearchResolveError> {
+        let context = ResolveContext::new(target, functions);
+
+        let matches = self.resolve_step(&context, Step::new(target, SearchMatch::default(), &self.parts, self.optional))?
+            .into_iter()
+            .map(|(found, _)| found)
+            .collect();
+
+        Ok(matches)
+    }
+
+    /// Resolves the search, returning the path of every match together with its value. The values
+    /// are borrowed while the search is resolved, so the document is only walked once.
+    pub fn resolve_values<'v>(&self, target: &'v Value) -> Result<Vec<(JsonPath, &'v Value)>, JsonSearchResolveError> {
+        self.resolve_values_with_functions(target, &FunctionRegistry::default())
+    }
+
+    pub fn resolve_values_with_functions<'v>(&self, target: &'v Value, functions: &FunctionRegistry) -> Result<Vec<(JsonPath, &'v Value)>, JsonSearchResolveError> {
+        let context = ResolveContext::new(target, functions);
+
+        let values = self.resolve_step(&context, Step::new(target, SearchMatch::default(), &self.parts, self.optional))?
+            .into_iter()
+            .map(|(found, value)| (found.into_path(), value))
+            .collect();
+
+        Ok(values)
+    }
+
+    /// Calls the function with the path and value of every match, so the matches can be edited in
+    /// place. The matches are edited during a single walk through the document, which resolves
+    /// the search along the way. Values that are matched more than once are only visited once,
+    /// and values nested inside other matches are visited before them, so replacing a match does
+    /// not skip the matches inside it. Other matches are visited in document order, and nothing
+    /// is edited when the search fails. Returns the number of values that were visited.
+    ///
+    /// Searches that move up with parent, ancestor or sibling parts, or whose filters query the
+    /// root or call user-defined functions, need the original document while resolving. They are
+    /// resolved first instead, after which a second walk follows the paths of the matches in the
+    /// order of those paths.
+    pub fn for_each_mut<F>(&self, target: &mut Value, f: F) -> Result<usize, JsonSearchResolveError>
+        where
+            F: FnMut(&JsonPath, &mut Value),
+    {
+        self.for_each_mut_with_functions(target, &FunctionRegistry::default(), f)
+    }
+
+    pub fn for_each_mut_with_functions<F>(&self, target: &mut Value, functions: &FunctionRegistry, mut f: F) -> Result<usize, JsonSearchResolveError>
+        where
+            F: FnMut(&JsonPath, &mut Value),
+    {
+        if self.parts.iter().all(|part| part.moves_down() && part.is_self_contained()) {
+            // A failed walk edited nothing, so resolving again reports the failure.
+            if let Some(edited) = EditWalk::new(self, functions, &mut f).run(target) {
+                return Ok(edited);
+            }
+        }
+
+        self.for_each_mut_resolved(target, functions, &mut f)
+    }
+
+    /// Edits the matches by resolving the search first, and then following the paths of the
+    /// matches in a second walk.
+    fn for_each_mut_resolved<F>(&self, target: &mut Value, functions: &FunctionRegistry, f: &mut F) -> Result<usize, JsonSearchResolveError>
+        where
+            F: FnMut(&JsonPath, &mut Value),
+    {
+        let mut paths = self.resolve_with_functions(target, functions)?;
+
+        paths.sort();
+        paths.dedup();
+
+        Ok(JsonPath::for_each_mut(&paths, &JsonPath::default(), target, f))
+    }
+
+    /// Replaces every match with a copy of the value.
+    pub fn set_all(&self, target: &mut Value, value: Value) -> Result<usize, JsonSearchResolveError> {
+        self.for_each_mut(target, |_, current| *current = value.clone())
+    }
+
+    /// Replaces every match with the result of the function, which receives the match by value.
+    pub fn map_all<F>(&self, target: &mut Value, mut f: F) -> Result<usize, JsonSearchResolveError>
+        where
+            F: FnMut(Value) -> Value,
+    {
+        self.for_each_mut(target, |_, current| *current = f(current.take()))
+    }
+
+    /// Calls the function with every match to change it in place.
+    pub fn update_all<F>(&self, target: &mut Value, mut f: F) -> Result<usize, JsonSearchResolveError>
+        where
+            F: FnMut(&mut Value),
+    {
+        self.for_each_mut(target, |_, current| f(current))
+    }
+
+    /// Resolves the search lazily, yielding the path of every match in document order as it is
+    /// found, so resolving can be stopped early. A failure is yielded as the last item, so
+    /// collecting the items into a `Result` gives the same result as [JsonSearch::resolve].
+    ///
+    /// Like with `resolve`, the matches of a branch of a wildcard or a similar part are left out
+    /// when the branch fails. Matches of a branch that can still fail are therefore only yielded
+    /// once the branch is done, like `$.b.x.0` for `$.*.x.[0,5]`, which is left out when
+    /// `$.b.x.5` turns out to be missing.
+    pub fn resolve_iter<'a>(&'a self, target: &'a Value) -> ResolveIter<'a> {
+        self.resolve_iter_with_functions(target, FunctionRegistry::empty())
+    }
+
+    pub fn resolve_iter_with_functions<'a>(&'a self, target: &'a Value, functions: &'a FunctionRegistry) -> ResolveIter<'a> {
+        ResolveIter::new(self, ResolveContext::new(target, functions), Step::new(target, SearchMatch::default(), &self.parts, self.optional))
+    }
+
+    /// Resolves parts leniently, returning the values of every match. Missing keys, missing indices
+    /// and mismatched types select nothing instead of returning an error, while failed calls to
+    /// user-defined functions are still returned.
+    pub(crate) fn select<'a>(context: &FilterContext, parts: &[SearchPart], target: &'a Value) -> Result<Vec<&'a Value>, JsonSearchResolveError> {
+        let search = JsonSearch {
+            parts: vec![],
+            optional: true,
+            lenient: true,
+        };
+
+        let context = ResolveContext {
+            filter: *context,
+            start: target,
+        };
 
-            results.extend(resolved);
+        let values = match search.resolve_step(&context, Step::new(target, SearchMatch::default(), parts, true)) {
+            Ok(found) => found.into_iter().map(|(_, value)| value).collect(),
+            Err(error) if error.is_function_error() => return Err(error),
+            Err(_) => vec![],
         };
 
+        Ok(values)
+    }
+
+    /// Resolves the remaining parts of a step, returning every match together with its value.
+    /// Steps that are a branch of a wildcard or a similar part are skipped when they fail, while
+    /// the failure of any other step fails the step it came from. Failed calls to user-defined
+    /// functions are never skipped.
+    fn resolve_step<'v>(&self, context: &ResolveContext<'_, 'v>, step: Step<'_, 'v>) -> Result<Vec<(SearchMatch, &'v Value)>, JsonSearchResolveError> {
+        if step.part.is_none() {
+            return Ok(vec![(step.found, step.value)]);
+        }
+
+        let mut results = vec![];
+        let mut steps = self.expand(context, step)?;
+
+        while let Some(next) = steps.next(self, context)? {
+            let branch = next.branch;
+
+            match self.resolve_step(context, next) {
+                Ok(found) => results.extend(found),
+                Err(error) if branch && !error.is_function_error() => {}
+                Err(error) => return Err(error),
+            }
+        }
+
         Ok(results)
     }
 
-    fn resolve_key(&self, parts: &[SearchPart], target: &Value, mut parent: JsonPath, key: &String) -> Result<Vec<JsonPath>, JsonSearchResolveError> {
-        let Value::Object(map) = target else {
-            return Err(JsonSearchResolveError::NotAnObject(parent));
+    /// Applies the next part of a step, returning the steps that continue from every value it
+    /// selects in document order.
+    pub(crate) fn expand<'s, 'v>(&self, context: &ResolveContext<'_, 'v>, step: Step<'s, 'v>) -> Result<Expansion<'s, 'v>, JsonSearchResolveError> {
+        let Some(part) = step.part else {
+            return Ok(Expansion::default());
+        };
+
+        match part {
+            SearchPart::Key(key) => self.resolve_key(step, key),
+            SearchPart::Index(index) => self.resolve_index(step, index),
+            SearchPart::Wildcard => self.resolve_children(step, ChildFilter::All),
+            SearchPart::Capture(name) => self.resolve_children(step, ChildFilter::Capture(name)),
+            SearchPart::RecursiveDescent => Ok(self.resolve_recursive_descent(step, DepthRange::default())),
+            SearchPart::BoundedDescent(range) => Ok(self.resolve_recursive_descent(step, *range)),
+            SearchPart::Slice(slice) => self.resolve_slice(step, slice),
+            SearchPart::Element(key) => self.resolve_element(step, key),
+            SearchPart::Union(members) => Ok(self.resolve_union(step, members)),
+            SearchPart::Filter(expression) => self.resolve_children(step, ChildFilter::Filter(expression)),
+            SearchPart::KeyPattern(pattern) => self.resolve_object_children(step, ChildFilter::KeyPattern(pattern)),
+            SearchPart::Exclusion(keys) => self.resolve_object_children(step, ChildFilter::Exclusion(keys)),
+            SearchPart::TypeGuard(value_type) => Ok(self.resolve_type_guard(step, value_type)),
+            SearchPart::Parent => self.resolve_parent(context, step),
+            SearchPart::Ancestors => self.resolve_ancestors(context, step),
+            SearchPart::Siblings => self.resolve_siblings(context, step),
+            SearchPart::Optional(inner) => Ok(Expansion::single(Step {
+                part: Some(inner),
+                optional: true,
+                ..step
+            })),
+        }
+    }
+
+    /// Continues with the parts after the current one at the given value.
+    fn next<'s, 'v>(&self, step: &Step<'s, 'v>, value: &'v Value, found: SearchMatch, branch: bool) -> Step<'s, 'v> {
+        Step {
+            branch,
+            ..Step::new(value, found, step.rest, self.optional)
+        }
+    }
+
+    fn resolve_key<'s, 'v>(&self, step: Step<'s, 'v>, key: &String) -> Result<Expansion<'s, 'v>, JsonSearchResolveError> {
+        let Value::Object(map) = step.value else {
+            return mismatch(self.lenient, JsonSearchResolveError::NotAnObject(step.found.path));
         };
 
         match map.get(key) {
             Some(value) => {
-                parent.push(PathPart::Key(key.clone()));
-                self.resolve_inner(parts, value, parent)
+                let mut found = step.found.clone();
+                found.push(PathPart::Key(key.clone()));
+
+                Ok(Expansion::single(self.next(&step, value, found, false)))
             },
-            None if self.optional => Ok(vec![]),
-            None => Err(JsonSearchResolveError::MissingRequiredKey(parent, key.to_string())),
+            None if step.optional => Ok(Expansion::default()),
+            None => Err(JsonSearchResolveError::MissingRequiredKey(step.found.path, key.to_string())),
         }
     }
 
-    fn resolve_index(&self, parts: &[SearchPart], target: &Value, mut parent: JsonPath, index: &usize) -> Result<Vec<JsonPath>, JsonSearchResolveError> {
-        let Value::Array(array) = target else {
-            return Err(JsonSearchResolveError::NotAnArray(parent));
+    fn resolve_index<'s, 'v>(&self, step: Step<'s, 'v>, index: &isize) -> Result<Expansion<'s, 'v>, JsonSearchResolveError> {
+        let Value::Array(array) = step.value else {
+            return mismatch(self.lenient, JsonSearchResolveError::NotAnArray(step.found.path));
         };
 
-        match array.get(*index) {
-            Some(value) => {
-                parent.push(PathPart::Index(*index));
-                self.resolve_inner(parts, value, parent)
+        match normalize_index(*index, array.len()) {
+            Some(position) => {
+                let mut found = step.found.clone();
+                found.push(PathPart::Index(position as isize));
+
+                Ok(Expansion::single(self.next(&step, &array[position], found, false)))
             },
-            None if self.optional => Ok(vec![]),
-            None => Err(JsonSearchResolveError::MissingRequiredIndex(parent, *index)),
+            None if step.optional => Ok(Expansion::default()),
+            None => Err(JsonSearchResolveError::MissingRequiredIndex(step.found.path, *index)),
         }
     }
 
-    fn resolve_wildcard(&self, parts: &[SearchPart], target: &Value, parent: JsonPath) -> Result<Vec<JsonPath>, JsonSearchResolveError> {
-        match target {
-            Value::Array(_) => self.resolve_array_wildcard(parts, target, parent),
-            Value::Object(_) => self.resolve_object_wildcard(parts, target, parent),
-            _ => Err(JsonSearchResolveError::NotAnArrayOrObject(parent)),
-        }
+    fn resolve_slice<'s, 'v>(&self, step: Step<'s, 'v>, slice: &ArraySlice) -> Result<Expansion<'s, 'v>, JsonSearchResolveError> {
+        let Value::Array(array) = step.value else {
+            return mismatch(self.lenient, JsonSearchResolveError::NotAnArray(step.found.path));
+        };
+
+        Ok(Expansion::Children {
+            parent: step.found.clone(),
+            cursor: Cursor::Slice(array, slice.iter(array.len())),
+            filter: ChildFilter::All,
+            origin: step,
+        })
     }
 
-    fn resolve_array_wildcard(&self, parts: &[SearchPart], target: &Value, parent: JsonPath) -> Result<Vec<JsonPath>, JsonSearchResolveError> {
-        let Value::Array(array) = target else {
-            return Err(JsonSearchResolveError::NotAnArray(parent));
+    fn resolve_element<'s, 'v>(&self, step: Step<'s, 'v>, key: &'s ElementKey) -> Result<Expansion<'s, 'v>, JsonSearchResolveError> {
+        let Value::Array(array) = step.value else {
+            return mismatch(self.lenient, JsonSearchResolveError::NotAnArray(step.found.path));
         };
 
-        let parts: Vec<Vec<JsonPath>> = array.iter()
-            .enumerate()
-            .filter_map(|(i, value)| {
-                let mut local = parent.clone();
-                local.push(PathPart::Index(i));
+        if key.position(array).is_none() {
+            return mismatch(step.optional, JsonSearchResolveError::MissingRequiredElement(step.found.path, key.clone()));
+        }
+
+        self.resolve_children(step, ChildFilter::Element(key))
+    }
 
-                self.resolve_inner(parts, value, local).ok()
+    fn resolve_union<'s, 'v>(&self, step: Step<'s, 'v>, members: &'s [SearchPart]) -> Expansion<'s, 'v> {
+        let steps: Vec<Step> = members.iter()
+            .map(|member| Step {
+                part: Some(member),
+                found: step.found.clone(),
+                ..step
             })
             .collect();
 
-        Ok(parts.into_iter().flatten().collect())
+        Expansion::Steps(steps.into_iter())
     }
 
-    fn resolve_object_wildcard(&self, parts: &[SearchPart], target: &Value, parent: JsonPath) -> Result<Vec<JsonPath>, JsonSearchResolveError> {
-        let Value::Object(map) = target else {
-            return Err(JsonSearchResolveError::NotAnObject(parent));
+    fn resolve_type_guard<'s, 'v>(&self, step: Step<'s, 'v>, value_type: &ValueType) -> Expansion<'s, 'v> {
+        match value_type.matches(step.value) {
+            true => Expansion::single(self.next(&step, step.value, step.found.clone(), false)),
+            false => Expansion::default(),
+        }
+    }
+
+    fn resolve_parent<'s, 'v>(&self, context: &ResolveContext<'_, 'v>, step: Step<'s, 'v>) -> Result<Expansion<'s, 'v>, JsonSearchResolveError> {
+        let Some(parent) = step.found.parent() else {
+            return mismatch(step.optional, JsonSearchResolveError::MissingParent(step.found.path));
         };
 
-        let parts: Vec<Vec<JsonPath>> = map.iter()
-            .filter_map(|(key, value)| {
-                let mut local = parent.clone();
-                local.push(PathPart::Key(key.to_string()));
+        let value = parent.path.resolve(context.start)
+            .map_err(|error| JsonSearchResolveError::FailedToResolveParent(parent.path.clone(), error))?;
 
-                self.resolve_inner(parts, value, local).ok()
-            })
-            .collect();
+        Ok(Expansion::single(self.next(&step, value, parent, false)))
+    }
+
+    /// Continues with every value containing the target, nearest first, as branches that are
+    /// skipped when they fail to resolve, like with wildcards.
+    fn resolve_ancestors<'s, 'v>(&self, context: &ResolveContext<'_, 'v>, step: Step<'s, 'v>) -> Result<Expansion<'s, 'v>, JsonSearchResolveError> {
+        let mut steps = vec![];
+        let mut ancestor = step.found.parent();
+
+        while let Some(found) = ancestor {
+            ancestor = found.parent();
+
+            let value = found.path.resolve(context.start)
+                .map_err(|error| JsonSearchResolveError::FailedToResolveParent(found.path.clone(), error))?;
+
+            steps.push(self.next(&step, value, found, true));
+        }
+
+        Ok(Expansion::Steps(steps.into_iter()))
+    }
+
+    fn resolve_siblings<'s, 'v>(&self, context: &ResolveContext<'_, 'v>, step: Step<'s, 'v>) -> Result<Expansion<'s, 'v>, JsonSearchResolveError> {
+        let (Some(parent), Some(current)) = (step.found.parent(), step.found.path.last().cloned()) else {
+            return mismatch(step.optional, JsonSearchResolveError::MissingParent(step.found.path));
+        };
+
+        let value = parent.path.resolve(context.start)
+            .map_err(|error| JsonSearchResolveError::FailedToResolveParent(parent.path.clone(), error))?;
+
+        Ok(Expansion::Children {
+            origin: step,
+            parent,
+            cursor: Cursor::new(value),
+            filter: ChildFilter::Except(current),
+        })
+    }
+
+    /// Continues with every child of an array or an object that is kept by the filter, as
+    /// branches that are skipped when they fail to resolve.
+    fn resolve_children<'s, 'v>(&self, step: Step<'s, 'v>, filter: ChildFilter<'s>) -> Result<Expansion<'s, 'v>, JsonSearchResolveError> {
+        match step.value {
+            Value::Array(_) | Value::Object(_) => Ok(Expansion::Children {
+                parent: step.found.clone(),
+                cursor: Cursor::new(step.value),
+                filter,
+                origin: step,
+            }),
+            _ => mismatch(self.lenient, JsonSearchResolveError::NotAnArrayOrObject(step.found.path)),
+        }
+    }
+
+    /// Continues with every child of an object that is kept by the filter, like
+    /// [JsonSearch::resolve_children].
+    fn resolve_object_children<'s, 'v>(&self, step: Step<'s, 'v>, filter: ChildFilter<'s>) -> Result<Expansion<'s, 'v>, JsonSearchResolveError> {
+        match step.value {
+            Value::Object(_) => self.resolve_children(step, filter),
+            _ => mismatch(self.lenient, JsonSearchResolveError::NotAnObject(step.found.path)),
+        }
+    }
+
+    /// Continues with the target itself and every value nested inside it that lies within the
+    /// range of levels, in document order. The descent itself is repeated for every child, so
+    /// values are only visited when they are needed.
+    fn resolve_recursive_descent<'s, 'v>(&self, step: Step<'s, 'v>, range: DepthRange) -> Expansion<'s, 'v> {
+        let cursor = match range.allows_below(step.depth) {
+            true => Cursor::new(step.value),
+            false => Cursor::Empty,
+        };
+
+        Expansion::Descent {
+            origin: step,
+            range,
+            started: false,
+            cursor,
+        }
+    }
+}
+
+/// State shared by every step of resolving a search.
+pub(crate) struct ResolveContext<'a, 'v> {
+    filter: FilterContext<'a>,
+
+    /// The value the search started at, which the paths of matches are relative to. Parent and
+    /// sibling parts look up the values of the paths they move to in here.
+    start: &'v Value,
+}
+
+impl<'a, 'v: 'a> ResolveContext<'a, 'v> {
+    fn new(target: &'v Value, functions: &'a FunctionRegistry) -> Self {
+        ResolveContext {
+            filter: FilterContext {
+                root: target,
+                key: None,
+                functions,
+            },
+            start: target,
+        }
+    }
+}
+
+/// A value that was reached while resolving a search, together with the parts that still have to
+/// be applied to it.
+pub(crate) struct Step<'s, 'v> {
+    value: &'v Value,
+    found: SearchMatch,
+
+    /// The part to apply next, which is the first of the remaining parts unless it is a member of
+    /// a union or the inside of an optional part.
+    part: Option<&'s SearchPart>,
+    rest: &'s [SearchPart],
+    optional: bool,
+
+    /// How many levels a recursive descent has gone down so far.
+    depth: usize,
+
+    /// Whether a failure of this step should skip it rather than fail the step it came from.
+    branch: bool,
+}
+
+impl<'s, 'v> Step<'s, 'v> {
+    fn new(value: &'v Value, found: SearchMatch, parts: &'s [SearchPart], optional: bool) -> Self {
+        Step {
+            value,
+            found,
+            part: parts.first(),
+            rest: parts.get(1..).unwrap_or_default(),
+            optional,
+            depth: 0,
+            branch: false,
+        }
+    }
+}
 
-        Ok(parts.into_iter().flatten().collect())
+/// Selects nothing when the failure of a part should be skipped, and otherwise returns the given
+/// error. Missing values are skipped by optional parts, while values of the wrong type are only
+/// skipped by lenient searches.
+fn mismatch<T: Default>(skip: bool, error: JsonSearchResolveError) -> Result<T, JsonSearchResolveError> {
+    match skip {
+        true => Ok(T::default()),
+        false => Err(error),
     }
 }
 
 #[derive(Debug, Error, PartialEq)]
 pub enum JsonSearchParseError {
-    #[error("JSON search string should have a '$' or '?' as the first character")]
+    #[error("JSON search string should have a '$', '?' or '~' as the first character")]
     MissingRoot,
 
-    #[error("JSON search string should start with a '$' or '?', but got '{0}'")]
+    #[error("JSON search string should start with a '$', '?' or '~', but got '{0}'")]
     IncorrectRoot(String),
+
+    #[error("Every stage after a pipe should start with '@', but got '{0}'")]
+    IncorrectStageRoot(String),
+
+    #[error("Invalid array slice '{0}', expected 'start:end' or 'start:end:step'")]
+    InvalidSlice(String),
+
+    #[error("Invalid union member '{0}', expected a key, index, slice or filter")]
+    InvalidUnionMember(String),
+
+    #[error("Invalid filter expression '{0}': {1}")]
+    InvalidFilter(String, String),
+
+    #[error("Invalid regular expression '{0}': {1}")]
+    InvalidRegex(String, String),
+
+    #[error("Invalid element key '{0}', expected a field and a value like 'id=42'")]
+    InvalidElementKey(String),
+
+    #[error("Invalid exclusion '{0}', expected a key or a list of keys like '{{secret,token}}'")]
+    InvalidExclusion(String),
+
+    #[error("Invalid depth range '{0}', expected '{{min,max}}', '{{min,}}', '{{,max}}' or '{{depth}}'")]
+    InvalidDepthRange(String),
+
+    #[error("Invalid JSONPath query '{0}' at position {1}: {2}")]
+    InvalidJsonPath(String, usize, String),
+
+    #[error("Empty segment in search, use '.**.' to search at any depth, like '$.**.id' instead of '$..id'")]
+    EmptySegment,
 }
 
 impl FromStr for JsonSearch {
     type Err = JsonSearchParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split('.');
-
-        let optional = match parts.next() {
-            Some("?") => Ok(true),
-            Some("$") => Ok(false),
-            Some(value) => Err(JsonSearchParseError::IncorrectRoot(value.to_string())),
-            None => Err(JsonSearchParseError::MissingRoot),
-        }?;
-
-        Ok(JsonSearch {
-            parts: parts
-                .map(|part| SearchPart::from(part.to_string()))
-                .collect(),
-            optional,
-        })
+        JsonSearch::parse(s, SearchSyntax::Native)
     }
 }
 
@@ -199,26 +725,32 @@ impl<const U: usize> From<[&str; U]> for JsonSearch {
                 .map(|part| part.to_string().into())
                 .collect(),
             optional: false,
+            lenient: false,
         }
     }
 }
 
 impl Display for JsonSearch {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        if self.optional {
+        if self.lenient {
+            write!(f, "~")?;
+        } else if self.optional {
             write!(f, "?")?;
         } else {
             write!(f, "$")?;
         }
 
         for part in &self.parts {
-            write!(f, "{}", part)?;
+            part.fmt_segment(f)?;
         }
 
         Ok(())
     }
 }
 
+/// Searches are serialized as strings. Searches that call user-defined functions are serialized
+/// the same way, but fail to deserialize as the functions are unknown there; read them back with
+/// [JsonSearch::parse_with_functions] instead.
 #[cfg(feature = "serde")]
 impl Serialize for JsonSearch {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -242,41 +774,240 @@ impl<'de> Deserialize<'de> for JsonSearch {
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
-    use serde_json::json;
-    use crate::json_path::JsonPath;
-    use crate::json_search::{JsonSearch, JsonSearchParseError, JsonSearchResolveError};
+    use quickcheck::{quickcheck, Arbitrary, Gen};
+    use serde_json::{json, Value};
+    use crate::json_path::{JsonPath, JsonPathResolveError};
+    use crate::json_path::element_key::ElementKey;
+    use crate::json_path::path_part::PathPart;
+    use crate::json_search::{JsonSearch, JsonSearchParseError, JsonSearchResolveError, ResolveContext, Step};
+    use crate::json_search::array_slice::ArraySlice;
+    use crate::json_search::depth_range::DepthRange;
+    use crate::json_search::edit_walk::EditWalk;
+    use crate::json_search::filter::{Comparable, ComparisonOperator, FilterExpression, FilterQuery};
+    use crate::json_search::filter_function::{FilterFunction, FunctionCall, FunctionType};
+    use crate::json_search::function_registry::FunctionRegistry;
+    use crate::json_search::key_pattern::KeyPattern;
+    use crate::json_search::search_match::SearchMatch;
+    use crate::json_search::value_type::ValueType;
     use crate::json_search::search_part::SearchPart;
+    use crate::json_search::search_syntax::SearchSyntax;
+
+    fn arbitrary_key(g: &mut Gen) -> String {
+        loop {
+            if let PathPart::Key(key) = PathPart::arbitrary(g) {
+                return key;
+            }
+        }
+    }
+
+    fn arbitrary_part(g: &mut Gen, depth: usize) -> SearchPart {
+        let value_types = [ValueType::Null, ValueType::Boolean, ValueType::Number, ValueType::String, ValueType::Array, ValueType::Object];
+
+        match u8::arbitrary(g) % if depth > 0 { 9 } else { 6 } {
+            3 if bool::arbitrary(g) => SearchPart::TypeGuard(*g.choose(&value_types).unwrap()),
+            0 if bool::arbitrary(g) => SearchPart::KeyPattern(g.choose(&["cpu_*", "*_total", "a*b*c", "/^disk_\\d+$/", "/a.b/", "/^[a-z]*\\/x$/"]).unwrap().parse().unwrap()),
+            1 if bool::arbitrary(g) => SearchPart::Exclusion((0..1 + usize::arbitrary(g) % 3).map(|_| arbitrary_key(g)).collect()),
+            0 | 1 => SearchPart::Key(arbitrary_key(g)),
+            2 => SearchPart::Index(isize::arbitrary(g)),
+            3 if bool::arbitrary(g) => SearchPart::Capture(g.choose(&["id", "tenant", "user_2"]).unwrap().to_string()),
+            3 => SearchPart::Wildcard,
+            4 if bool::arbitrary(g) => g.choose(&[SearchPart::Parent, SearchPart::Ancestors, SearchPart::Siblings]).unwrap().clone(),
+            4 if bool::arbitrary(g) => {
+                let min = usize::arbitrary(g) % 4;
+                SearchPart::BoundedDescent(DepthRange::new(min, Option::<usize>::arbitrary(g).map(|extra| min + extra % 4)))
+            }
+            4 => SearchPart::RecursiveDescent,
+            5 if bool::arbitrary(g) => SearchPart::Element(ElementKey::new(arbitrary_key(g), i64::arbitrary(g))),
+            5 => SearchPart::Slice(ArraySlice::new(Option::arbitrary(g), Option::arbitrary(g), Option::arbitrary(g))),
+            6 => SearchPart::Filter(arbitrary_expression(g, depth - 1)),
+            7 => match arbitrary_part(g, depth - 1) {
+                SearchPart::TypeGuard(value_type) => SearchPart::TypeGuard(value_type),
+                part => SearchPart::Optional(Box::new(part)),
+            },
+            _ => {
+                let length = 2 + usize::arbitrary(g) % 3;
+
+                SearchPart::Union((0..length)
+                    .map(|_| match arbitrary_part(g, depth - 1) {
+                        SearchPart::RecursiveDescent | SearchPart::BoundedDescent(_) | SearchPart::Union(_) | SearchPart::TypeGuard(_) => SearchPart::Wildcard,
+                        SearchPart::Optional(inner) if !is_simple_part(&inner) => SearchPart::Wildcard,
+                        member => member,
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    fn is_simple_part(part: &SearchPart) -> bool {
+        matches!(part, SearchPart::Key(_) | SearchPart::Index(_) | SearchPart::Wildcard | SearchPart::Slice(_))
+    }
+
+    fn arbitrary_query(g: &mut Gen, depth: usize) -> FilterQuery {
+        let length = usize::arbitrary(g) % 3;
+
+        FilterQuery {
+            relative: bool::arbitrary(g),
+            parts: (0..length).map(|_| arbitrary_part(g, depth)).collect(),
+        }
+    }
+
+    fn arbitrary_singular_query(g: &mut Gen) -> FilterQuery {
+        let length = usize::arbitrary(g) % 3;
+
+        FilterQuery {
+            relative: bool::arbitrary(g),
+            parts: (0..length)
+                .map(|_| match bool::arbitrary(g) {
+                    true => SearchPart::Key(arbitrary_key(g)),
+                    false => SearchPart::Index(isize::arbitrary(g)),
+                })
+                .collect(),
+        }
+    }
+
+    fn arbitrary_call(g: &mut Gen, depth: usize) -> FunctionCall {
+        match u8::arbitrary(g) % 5 {
+            0 => FunctionCall::new(FilterFunction::Length, vec![Comparable::Query(arbitrary_singular_query(g))]),
+            1 => FunctionCall::new(FilterFunction::Count, vec![Comparable::Query(arbitrary_query(g, depth))]),
+            2 => FunctionCall::new(FilterFunction::Value, vec![Comparable::Query(arbitrary_query(g, depth))]),
+            function => FunctionCall::new(
+                if function == 3 { FilterFunction::Match } else { FilterFunction::Search },
+                vec![Comparable::Query(arbitrary_singular_query(g)), Comparable::Literal(Value::String(arbitrary_key(g)))],
+            ),
+        }.unwrap()
+    }
+
+    fn arbitrary_comparable(g: &mut Gen, depth: usize) -> Comparable {
+        match u8::arbitrary(g) % 7 {
+            0 => Comparable::Query(arbitrary_query(g, depth)),
+            6 => Comparable::Key,
+            5 => match arbitrary_call(g, depth) {
+                call if call.result() == FunctionType::Value => Comparable::Function(call),
+                _ => Comparable::Query(arbitrary_singular_query(g)),
+            },
+            1 => Comparable::Literal(Value::String(arbitrary_key(g))),
+            2 => Comparable::Literal(json!(i64::arbitrary(g))),
+            3 => Comparable::Literal(json!(f64::arbitrary(g)).as_f64().map_or(Value::Null, |value| json!(value))),
+            _ => Comparable::Literal(json!(bool::arbitrary(g))),
+        }
+    }
+
+    fn arbitrary_expression(g: &mut Gen, depth: usize) -> FilterExpression {
+        let operators = [
+            ComparisonOperator::Equal,
+            ComparisonOperator::NotEqual,
+            ComparisonOperator::Less,
+            ComparisonOperator::LessOrEqual,
+            ComparisonOperator::Greater,
+            ComparisonOperator::GreaterOrEqual,
+        ];
+
+        match u8::arbitrary(g) % if depth > 0 { 5 } else { 2 } {
+            0 if bool::arbitrary(g) => match arbitrary_call(g, depth) {
+                call if call.result() == FunctionType::Logical => FilterExpression::Function(call),
+                _ => FilterExpression::Exists(arbitrary_query(g, depth)),
+            },
+            0 => FilterExpression::Exists(arbitrary_query(g, depth)),
+            1 => FilterExpression::Comparison(arbitrary_comparable(g, depth), *g.choose(&operators).unwrap(), arbitrary_comparable(g, depth)),
+            2 => FilterExpression::Not(Box::new(arbitrary_expression(g, depth - 1))),
+            3 => FilterExpression::And(Box::new(arbitrary_expression(g, depth - 1)), Box::new(arbitrary_expression(g, depth - 1))),
+            _ => FilterExpression::Or(Box::new(arbitrary_expression(g, depth - 1)), Box::new(arbitrary_expression(g, depth - 1))),
+        }
+    }
+
+    impl Arbitrary for JsonSearch {
+        fn arbitrary(g: &mut Gen) -> Self {
+            let length = usize::arbitrary(g) % 6;
+
+            let lenient = bool::arbitrary(g);
+
+            JsonSearch {
+                parts: (0..length).map(|_| arbitrary_part(g, 2)).collect(),
+                optional: lenient || bool::arbitrary(g),
+                lenient,
+            }
+        }
+    }
+
+    #[test]
+    fn searches_round_trip_through_display_and_from_str() {
+        fn round_trips(search: JsonSearch) -> bool {
+            JsonSearch::from_str(&search.to_string()) == Ok(search)
+        }
+
+        quickcheck(round_trips as fn(JsonSearch) -> bool);
+    }
+
+    #[test]
+    fn lazily_resolved_searches_collect_into_the_result_of_resolve() {
+        // Random keys and indices rarely exist, so searches are also built from segments that do.
+        fn agrees(search: JsonSearch, segments: Vec<u8>, optional: bool) -> bool {
+            let names = ["a", "b", "c", "0", "1", "-1", "*", "**", "^", "^*", "^^", "[0,5]", "[0:2]", "c?", "[?@.a]", "{k}", "!a", "[b,d]"];
+            let built = segments.iter()
+                .map(|i| names[*i as usize % names.len()])
+                .fold(if optional { "?".to_string() } else { "$".to_string() }, |search, segment| search + "." + segment);
+
+            let target_value = json!({
+                "a": { "b": [1, { "c": "x", "0": null }], "-1": [true, [2, 3]] },
+                "b": [{ "a": 1 }, "e", { "b": { "c": [] } }],
+                "c": { "a": { "a": 1.5 } },
+            });
+
+            [search, JsonSearch::from_str(&built).unwrap()].iter().all(|search| {
+                search.resolve_iter(&target_value).collect::<Result<Vec<_>, _>>() == search.resolve(&target_value)
+            })
+        }
+
+        quickcheck(agrees as fn(JsonSearch, Vec<u8>, bool) -> bool);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn searches_round_trip_through_serde() {
+        fn round_trips(search: JsonSearch) -> bool {
+            let serialized = serde_json::to_string(&search).unwrap();
+            serde_json::from_str::<JsonSearch>(&serialized).ok() == Some(search)
+        }
+
+        quickcheck(round_trips as fn(JsonSearch) -> bool);
+    }
 
     #[test]
     fn correctly_formatted_json_search_strings_are_parsed_correctly() {
         assert_eq!(JsonSearch::from_str("$").unwrap(), JsonSearch {
             parts: vec![],
             optional: false,
+            lenient: false,
         });
 
         assert_eq!(JsonSearch::from_str("$.a").unwrap(), JsonSearch {
             parts: vec![SearchPart::Key("a".to_string())],
             optional: false,
+            lenient: false,
         });
 
         assert_eq!(JsonSearch::from_str("$.a.b").unwrap(), JsonSearch {
             parts: vec![SearchPart::Key("a".to_string()), SearchPart::Key("b".to_string())],
             optional: false,
+            lenient: false,
         });
 
         assert_eq!(JsonSearch::from_str("$.0").unwrap(), JsonSearch {
             parts: vec![SearchPart::Index(0)],
             optional: false,
+            lenient: false,
         });
 
         assert_eq!(JsonSearch::from_str("$.*.a").unwrap(), JsonSearch {
             parts: vec![SearchPart::Wildcard, SearchPart::Key("a".to_string())],
             optional: false,
+            lenient: false,
         });
 
         assert_eq!(JsonSearch::from_str("?.*.a").unwrap(), JsonSearch {
             parts: vec![SearchPart::Wildcard, SearchPart::Key("a".to_string())],
             optional: true,
+            lenient: false,
         });
     }
 
@@ -286,6 +1017,17 @@ mod tests {
         assert_eq!(JsonSearch::from_str("!"), Err(JsonSearchParseError::IncorrectRoot("!".to_string())));
     }
 
+    #[test]
+    fn searches_built_from_segments_only_read_wildcards_and_indices() {
+        let target_value = json!({ "!x": 1, "y": 2, "a?": 3, "x*": 4, "xy": 5, "**": 6 });
+
+        assert_eq!(JsonSearch::from(["!x"]).resolve(&target_value), Ok(vec![JsonPath::from(["!x"])]));
+        assert_eq!(JsonSearch::from(["a?"]).resolve(&target_value), Ok(vec![JsonPath::from(["a?"])]));
+        assert_eq!(JsonSearch::new(&["x*"]).resolve(&target_value), Ok(vec![JsonPath::from(["x*"])]));
+        assert_eq!(JsonSearch::new(&["**"]).resolve(&target_value), Ok(vec![JsonPath::from(["**"])]));
+        assert_eq!(JsonSearch::from(["*", "0"]), JsonSearch::from_str("$.*.0").unwrap());
+    }
+
     #[test]
     fn root_value_is_resolved_correctly() {
         let target_value = json!("Hello world");
@@ -536,6 +1278,1163 @@ mod tests {
         ]));
     }
 
+    #[test]
+    fn recursive_descent_is_parsed_correctly() {
+        assert_eq!(JsonSearch::from_str("$.**.id").unwrap(), JsonSearch {
+            parts: vec![SearchPart::RecursiveDescent, SearchPart::Key("id".to_string())],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(JsonSearch::from_str("$..id"), Err(JsonSearchParseError::EmptySegment));
+        assert_eq!(JsonSearch::from_str("$.a..id"), Err(JsonSearchParseError::EmptySegment));
+        assert_eq!(JsonSearch::from_str("$.a."), Err(JsonSearchParseError::EmptySegment));
+        assert_eq!(JsonSearch::from_str("$[?@..id]"), Err(JsonSearchParseError::EmptySegment));
+        assert_eq!(JsonSearch::from_str(r#"$[""].id"#).unwrap(), JsonSearch::from(["", "id"]));
+    }
+
+    #[test]
+    fn recursive_descent_finds_keys_at_any_depth() {
+        let target_value = json!({
+            "id": 1,
+            "a": { "id": 2, "b": [{ "id": 3 }, { "c": 10 }] },
+            "d": [{ "e": { "id": 4 } }],
+        });
+
+        let search = JsonSearch::from_str("$.**.id").unwrap();
+
+        let result = search.resolve(&target_value);
+
+        assert_eq!(result, Ok(vec![
+            JsonPath::from(["id"]),
+            JsonPath::from(["a", "id"]),
+            JsonPath::from(["a", "b", "0", "id"]),
+            JsonPath::from(["d", "0", "e", "id"]),
+        ]));
+    }
+
+    #[test]
+    fn trailing_recursive_descent_returns_every_descendant() {
+        let target_value = json!({ "a": [10, { "b": 20 }] });
+        let search = JsonSearch::from_str("$.a.**").unwrap();
+
+        let result = search.resolve(&target_value);
+
+        assert_eq!(result, Ok(vec![
+            JsonPath::from(["a"]),
+            JsonPath::from(["a", "0"]),
+            JsonPath::from(["a", "1"]),
+            JsonPath::from(["a", "1", "b"]),
+        ]));
+    }
+
+    #[test]
+    fn slices_are_parsed_correctly() {
+        assert_eq!(JsonSearch::from_str("$.items.[1:10:2]").unwrap(), JsonSearch {
+            parts: vec![SearchPart::Key("items".to_string()), SearchPart::Slice(ArraySlice::new(Some(1), Some(10), Some(2)))],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(JsonSearch::from_str("$.[1:x]"), Err(JsonSearchParseError::InvalidSlice("1:x".to_string())));
+    }
+
+    #[test]
+    fn slices_are_resolved_correctly() {
+        let target_value = json!({ "items": [10, 20, 30, 40, 50, 60] });
+
+        assert_eq!(JsonSearch::from_str("$.items.[:3]").unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from(["items", "0"]),
+            JsonPath::from(["items", "1"]),
+            JsonPath::from(["items", "2"]),
+        ]));
+
+        assert_eq!(JsonSearch::from_str("$.items.[1:10:2]").unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from(["items", "1"]),
+            JsonPath::from(["items", "3"]),
+            JsonPath::from(["items", "5"]),
+        ]));
+
+        assert_eq!(JsonSearch::from_str("$.items.[::-2]").unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from(["items", "5"]),
+            JsonPath::from(["items", "3"]),
+            JsonPath::from(["items", "1"]),
+        ]));
+
+        assert_eq!(JsonSearch::from_str("$.items.[-2:]").unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from(["items", "4"]),
+            JsonPath::from(["items", "5"]),
+        ]));
+
+        assert_eq!(JsonSearch::from_str("$.items.[1:2:9223372036854775807]").unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from(["items", "1"]),
+        ]));
+
+        assert_eq!(
+            JsonSearch::from_str("$.[:2]").unwrap().resolve(&target_value),
+            Err(JsonSearchResolveError::NotAnArray(JsonPath::default())),
+        );
+    }
+
+    #[test]
+    fn negative_indices_are_resolved_from_the_end() {
+        let target_value = json!({ "events": [10, 20, 30] });
+
+        assert_eq!(JsonSearch::from_str("$.events.-1").unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from(["events", "2"]),
+        ]));
+
+        assert_eq!(JsonSearch::from_str("$.events.-3").unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from(["events", "0"]),
+        ]));
+
+        assert_eq!(
+            JsonSearch::from_str("$.events.-4").unwrap().resolve(&target_value),
+            Err(JsonSearchResolveError::MissingRequiredIndex(JsonPath::from(["events"]), -4)),
+        );
+
+        assert_eq!(JsonSearch::from_str("?.events.-4").unwrap().resolve(&target_value), Ok(vec![]));
+    }
+
+    #[test]
+    fn unions_are_parsed_correctly() {
+        assert_eq!(JsonSearch::from_str("$.user.[name,email]").unwrap(), JsonSearch {
+            parts: vec![
+                SearchPart::Key("user".to_string()),
+                SearchPart::Union(vec![SearchPart::Key("name".to_string()), SearchPart::Key("email".to_string())]),
+            ],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(JsonSearch::from_str("$.rows.[0, 2, :1]").unwrap(), JsonSearch {
+            parts: vec![
+                SearchPart::Key("rows".to_string()),
+                SearchPart::Union(vec![
+                    SearchPart::Index(0),
+                    SearchPart::Index(2),
+                    SearchPart::Slice(ArraySlice::new(None, Some(1), None)),
+                ]),
+            ],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(JsonSearch::from_str("$.rows.[3]").unwrap(), JsonSearch {
+            parts: vec![SearchPart::Key("rows".to_string()), SearchPart::Index(3)],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(JsonSearch::from_str("$.[a,]"), Err(JsonSearchParseError::InvalidUnionMember("".to_string())));
+        assert_eq!(JsonSearch::from_str("$.[0,2]").unwrap().parts[0].to_string(), "[0,2]");
+    }
+
+    #[test]
+    fn unions_are_resolved_in_the_listed_order() {
+        let target_value = json!({
+            "user": { "name": "a", "email": "b", "age": 10 },
+            "rows": [10, 20, 30, 40, 50, 60],
+        });
+
+        assert_eq!(JsonSearch::from_str("$.user.[email,name]").unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from(["user", "email"]),
+            JsonPath::from(["user", "name"]),
+        ]));
+
+        assert_eq!(JsonSearch::from_str("$.rows.[5,0,2]").unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from(["rows", "5"]),
+            JsonPath::from(["rows", "0"]),
+            JsonPath::from(["rows", "2"]),
+        ]));
+
+        assert_eq!(
+            JsonSearch::from_str("$.user.[name,phone]").unwrap().resolve(&target_value),
+            Err(JsonSearchResolveError::MissingRequiredKey(JsonPath::from(["user"]), "phone".to_string())),
+        );
+
+        assert_eq!(JsonSearch::from_str("?.user.[name,phone]").unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from(["user", "name"]),
+        ]));
+    }
+
+    #[test]
+    fn filters_are_parsed_correctly() {
+        let search = JsonSearch::from_str(r#"$.orders.[?(@.total > 100 && @.status == "open")]"#).unwrap();
+
+        assert_eq!(search, JsonSearch {
+            parts: vec![
+                SearchPart::Key("orders".to_string()),
+                SearchPart::Filter(FilterExpression::And(
+                    Box::new(FilterExpression::Comparison(
+                        Comparable::Query(FilterQuery { relative: true, parts: vec![SearchPart::Key("total".to_string())] }),
+                        ComparisonOperator::Greater,
+                        Comparable::Literal(json!(100)),
+                    )),
+                    Box::new(FilterExpression::Comparison(
+                        Comparable::Query(FilterQuery { relative: true, parts: vec![SearchPart::Key("status".to_string())] }),
+                        ComparisonOperator::Equal,
+                        Comparable::Literal(json!("open")),
+                    )),
+                )),
+            ],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(search.parts[1].to_string(), r#"[?@.total > 100 && @.status == "open"]"#);
+
+        assert!(matches!(JsonSearch::from_str("$.[?@.a ==]"), Err(JsonSearchParseError::InvalidFilter(_, _))));
+        assert!(matches!(JsonSearch::from_str("$.[?(@.a]"), Err(JsonSearchParseError::InvalidFilter(_, _))));
+        assert!(matches!(JsonSearch::from_str("$.[?10]"), Err(JsonSearchParseError::InvalidFilter(_, _))));
+        assert!(matches!(JsonSearch::from_str("$.[?a == 1]"), Err(JsonSearchParseError::InvalidFilter(_, _))));
+    }
+
+    #[test]
+    fn filters_select_matching_children() {
+        let target_value = json!({
+            "limit": 50,
+            "orders": [
+                { "total": 150, "status": "open", "tags": ["a"] },
+                { "total": 20, "status": "open" },
+                { "total": 300, "status": "closed", "tags": [] },
+                { "total": 101.5, "status": "open" },
+                { "status": "open" },
+            ],
+        });
+
+        let resolve = |search: &str| JsonSearch::from_str(search).unwrap().resolve(&target_value);
+
+        assert_eq!(resolve(r#"$.orders.[?(@.total > 100 && @.status == "open")]"#), Ok(vec![
+            JsonPath::from(["orders", "0"]),
+            JsonPath::from(["orders", "3"]),
+        ]));
+
+        assert_eq!(resolve(r#"$.orders.[?@.status != 'open' || @.total <= 20].total"#), Ok(vec![
+            JsonPath::from(["orders", "1", "total"]),
+            JsonPath::from(["orders", "2", "total"]),
+        ]));
+
+        assert_eq!(resolve("$.orders.[?@.tags]"), Ok(vec![
+            JsonPath::from(["orders", "0"]),
+            JsonPath::from(["orders", "2"]),
+        ]));
+
+        assert_eq!(resolve("$.orders.[?!@.total]"), Ok(vec![
+            JsonPath::from(["orders", "4"]),
+        ]));
+
+        assert_eq!(resolve("$.orders.[?@.total < $.limit]"), Ok(vec![
+            JsonPath::from(["orders", "1"]),
+        ]));
+
+        assert_eq!(resolve("$.orders.[?@.tags == $.orders.2.tags]"), Ok(vec![
+            JsonPath::from(["orders", "2"]),
+        ]));
+
+        assert_eq!(resolve("$.[?@ == 50]"), Ok(vec![
+            JsonPath::from(["limit"]),
+        ]));
+
+        assert_eq!(
+            resolve("$.limit.[?@.a]"),
+            Err(JsonSearchResolveError::NotAnArrayOrObject(JsonPath::from(["limit"]))),
+        );
+    }
+
+    #[test]
+    fn large_integers_are_compared_exactly() {
+        let target_value = json!({
+            "ids": [9007199254740992_u64, 9007199254740993_u64, 18446744073709551615_u64, -9223372036854775808_i64, 1.0],
+        });
+
+        let resolve = |search: &str| JsonSearch::from_str(search).unwrap().resolve(&target_value);
+
+        assert_eq!(resolve("$.ids.[?@ == 9007199254740993]"), Ok(vec![JsonPath::from(["ids", "1"])]));
+        assert_eq!(resolve("$.ids.[?@ < 9007199254740993]"), Ok(vec![
+            JsonPath::from(["ids", "0"]),
+            JsonPath::from(["ids", "3"]),
+            JsonPath::from(["ids", "4"]),
+        ]));
+        assert_eq!(resolve("$.ids.[?@ > 9007199254740993]"), Ok(vec![JsonPath::from(["ids", "2"])]));
+        assert_eq!(resolve("$.ids.[?@ == 1]"), Ok(vec![JsonPath::from(["ids", "4"])]));
+    }
+
+    #[test]
+    fn filter_functions_are_parsed_correctly() {
+        let search = JsonSearch::from_str(r#"$.hosts[?match(@.name, "web-[0-9]+") && length(@.roles) > 2]"#).unwrap();
+
+        assert_eq!(search.parts[1], SearchPart::Filter(FilterExpression::And(
+            Box::new(FilterExpression::Function(FunctionCall::new(FilterFunction::Match, vec![
+                Comparable::Query(FilterQuery { relative: true, parts: vec![SearchPart::Key("name".to_string())] }),
+                Comparable::Literal(json!("web-[0-9]+")),
+            ]).unwrap())),
+            Box::new(FilterExpression::Comparison(
+                Comparable::Function(FunctionCall::new(FilterFunction::Length, vec![
+                    Comparable::Query(FilterQuery { relative: true, parts: vec![SearchPart::Key("roles".to_string())] }),
+                ]).unwrap()),
+                ComparisonOperator::Greater,
+                Comparable::Literal(json!(2)),
+            )),
+        )));
+
+        assert_eq!(search.to_string(), r#"$.hosts[?match(@.name, "web-[0-9]+") && length(@.roles) > 2]"#);
+
+        let invalid = |s: &str| matches!(JsonSearch::from_str(s), Err(JsonSearchParseError::InvalidFilter(_, _)));
+
+        assert!(invalid("$[?size(@) == 1]"));
+        assert!(invalid("$[?length(@, @) == 1]"));
+        assert!(invalid("$[?length(@.*) == 1]"));
+        assert!(invalid("$[?length(@.a)]"));
+        assert!(invalid("$[?match(@.a, 'x') == true]"));
+        assert!(invalid("$[?count(1) == 1]"));
+    }
+
+    #[test]
+    fn filter_functions_are_evaluated_correctly() {
+        let target_value = json!({
+            "users": [
+                { "name": "a", "roles": ["admin", "dev", "ops"] },
+                { "name": "b", "roles": ["dev"] },
+                { "name": "ccc", "roles": {} },
+            ],
+            "hosts": [{ "name": "web-1" }, { "name": "web-x" }, { "name": "db-web-2" }],
+        });
+
+        let resolve = |search: &str| JsonSearch::from_str(search).unwrap().resolve(&target_value);
+
+        assert_eq!(resolve("$.users[?length(@.roles) > 2]"), Ok(vec![JsonPath::from(["users", "0"])]));
+        assert_eq!(resolve("$.users[?length(@.name) == 3]"), Ok(vec![JsonPath::from(["users", "2"])]));
+        assert_eq!(resolve("$.users[?count(@.roles.*) == 1]"), Ok(vec![JsonPath::from(["users", "1"])]));
+        assert_eq!(resolve(r#"$.users[?value(@.roles.*) == "dev"]"#), Ok(vec![JsonPath::from(["users", "1"])]));
+        assert_eq!(resolve(r#"$.hosts[?match(@.name, "web-[0-9]+")]"#), Ok(vec![JsonPath::from(["hosts", "0"])]));
+
+        assert_eq!(resolve(r#"$.hosts[?search(@.name, "web-[0-9]")].name"#), Ok(vec![
+            JsonPath::from(["hosts", "0", "name"]),
+            JsonPath::from(["hosts", "2", "name"]),
+        ]));
+
+        assert_eq!(resolve(r#"$.hosts[?!match(@.name, "web-.*")]"#), Ok(vec![JsonPath::from(["hosts", "2"])]));
+    }
+
+    #[test]
+    fn bracket_notation_is_parsed_correctly() {
+        assert_eq!(JsonSearch::from_str(r#"$["a.b"].*['0']"#).unwrap(), JsonSearch {
+            parts: vec![SearchPart::Key("a.b".to_string()), SearchPart::Wildcard, SearchPart::Key("0".to_string())],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(JsonSearch::from_str(r#"$["*"]["?"][*]"#).unwrap(), JsonSearch {
+            parts: vec![SearchPart::Key("*".to_string()), SearchPart::Key("?".to_string()), SearchPart::Wildcard],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(JsonSearch::from_str(r#"$.user["first name", 'e,mail']"#).unwrap(), JsonSearch {
+            parts: vec![
+                SearchPart::Key("user".to_string()),
+                SearchPart::Union(vec![SearchPart::Key("first name".to_string()), SearchPart::Key("e,mail".to_string())]),
+            ],
+            optional: false,
+            lenient: false,
+        });
+    }
+
+    #[test]
+    fn searches_are_displayed_in_a_format_that_can_be_parsed_again() {
+        let search = JsonSearch {
+            parts: vec![
+                SearchPart::Key("a".to_string()),
+                SearchPart::Key("a.b".to_string()),
+                SearchPart::Key("*".to_string()),
+                SearchPart::Wildcard,
+                SearchPart::Key("0".to_string()),
+                SearchPart::Index(0),
+                SearchPart::Union(vec![SearchPart::Key("x]".to_string()), SearchPart::Key("y".to_string())]),
+                SearchPart::Slice(ArraySlice::new(None, Some(2), None)),
+            ],
+            optional: true,
+            lenient: false,
+        };
+
+        assert_eq!(search.to_string(), r#"?.a["a.b"]["*"].*["0"].0["x]",y][:2]"#);
+        assert_eq!(JsonSearch::from_str(&search.to_string()), Ok(search));
+    }
+
+    #[test]
+    fn quoted_keys_are_resolved_correctly() {
+        let target_value = json!({ "a.b": { "0": 10, "*": 20 } });
+
+        assert_eq!(JsonSearch::from_str(r#"$["a.b"]['0']"#).unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from_str(r#"$["a.b"]["0"]"#).unwrap(),
+        ]));
+
+        assert_eq!(JsonSearch::from_str(r#"$["a.b"]["*"]"#).unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from_str(r#"$["a.b"]["*"]"#).unwrap(),
+        ]));
+    }
+
+    #[test]
+    fn optional_parts_are_parsed_correctly() {
+        assert_eq!(JsonSearch::from_str("$.user.nickname?").unwrap(), JsonSearch {
+            parts: vec![
+                SearchPart::Key("user".to_string()),
+                SearchPart::Optional(Box::new(SearchPart::Key("nickname".to_string()))),
+            ],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(JsonSearch::from_str(r#"$.items[0]?["a?"].[b?,'c']"#).unwrap(), JsonSearch {
+            parts: vec![
+                SearchPart::Key("items".to_string()),
+                SearchPart::Optional(Box::new(SearchPart::Index(0))),
+                SearchPart::Key("a?".to_string()),
+                SearchPart::Union(vec![
+                    SearchPart::Optional(Box::new(SearchPart::Key("b".to_string()))),
+                    SearchPart::Key("c".to_string()),
+                ]),
+            ],
+            optional: false,
+            lenient: false,
+        });
+    }
+
+    #[test]
+    fn optional_parts_are_skipped_when_missing() {
+        let search = JsonSearch::from_str("$.user.nickname?").unwrap();
+
+        assert_eq!(search.resolve(&json!({ "user": { "nickname": "a" } })), Ok(vec![
+            JsonPath::from(["user", "nickname"]),
+        ]));
+
+        assert_eq!(search.resolve(&json!({ "user": {} })), Ok(vec![]));
+
+        assert_eq!(
+            search.resolve(&json!({})),
+            Err(JsonSearchResolveError::MissingRequiredKey(JsonPath::default(), "user".to_string())),
+        );
+
+        let search = JsonSearch::from_str("$.rows.5?.name").unwrap();
+
+        assert_eq!(search.resolve(&json!({ "rows": [] })), Ok(vec![]));
+
+        assert_eq!(
+            search.resolve(&json!({ "rows": [0, 1, 2, 3, 4, {}] })),
+            Err(JsonSearchResolveError::MissingRequiredKey(JsonPath::from(["rows", "5"]), "name".to_string())),
+        );
+
+        assert_eq!(JsonSearch::from_str("$.[a,b?]").unwrap().resolve(&json!({ "a": 1 })), Ok(vec![
+            JsonPath::from(["a"]),
+        ]));
+    }
+
+    #[test]
+    fn key_patterns_are_parsed_correctly() {
+        assert_eq!(JsonSearch::from_str(r"$.metrics.cpu_*./^disk_\d+\.[a-z]$/").unwrap(), JsonSearch {
+            parts: vec![
+                SearchPart::Key("metrics".to_string()),
+                SearchPart::KeyPattern(KeyPattern::Glob("cpu_*".to_string())),
+                SearchPart::KeyPattern(KeyPattern::from_str(r"/^disk_\d+\.[a-z]$/").unwrap()),
+            ],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(JsonSearch::from_str(r#"$["cpu_*"]"#).unwrap(), JsonSearch {
+            parts: vec![SearchPart::Key("cpu_*".to_string())],
+            optional: false,
+            lenient: false,
+        });
+
+        assert!(matches!(JsonSearch::from_str("$./(/"), Err(JsonSearchParseError::InvalidRegex(_, _))));
+    }
+
+    #[test]
+    fn key_patterns_only_match_keys_of_objects() {
+        let target_value = json!({
+            "metrics": {
+                "cpu_1": 10,
+                "cpu_2": 20,
+                "disk_1": 30,
+                "disk_a": 40,
+                "memory": 50,
+            },
+            "workers": [{ "worker_1": 10 }],
+        });
+
+        let resolve = |search: &str| JsonSearch::from_str(search).unwrap().resolve(&target_value);
+
+        assert_eq!(resolve("$.metrics.cpu_*"), Ok(vec![
+            JsonPath::from(["metrics", "cpu_1"]),
+            JsonPath::from(["metrics", "cpu_2"]),
+        ]));
+
+        assert_eq!(resolve(r"$.metrics./^disk_\d+$/"), Ok(vec![
+            JsonPath::from(["metrics", "disk_1"]),
+        ]));
+
+        assert_eq!(resolve("$.metrics.gpu_*"), Ok(vec![]));
+
+        assert_eq!(
+            resolve("$.workers.worker_*"),
+            Err(JsonSearchResolveError::NotAnObject(JsonPath::from(["workers"]))),
+        );
+    }
+
+    #[test]
+    fn type_guards_are_parsed_correctly() {
+        assert_eq!(JsonSearch::from_str("$.labels.*:string").unwrap(), JsonSearch {
+            parts: vec![
+                SearchPart::Key("labels".to_string()),
+                SearchPart::Wildcard,
+                SearchPart::TypeGuard(ValueType::String),
+            ],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(JsonSearch::from_str("$.*{}.*[].a?:number:null").unwrap(), JsonSearch {
+            parts: vec![
+                SearchPart::Wildcard,
+                SearchPart::TypeGuard(ValueType::Object),
+                SearchPart::Wildcard,
+                SearchPart::TypeGuard(ValueType::Array),
+                SearchPart::Optional(Box::new(SearchPart::Key("a".to_string()))),
+                SearchPart::TypeGuard(ValueType::Number),
+                SearchPart::TypeGuard(ValueType::Null),
+            ],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(JsonSearch::from_str("$.*{}.*[]").unwrap().to_string(), "$.*:object.*:array");
+
+        assert_eq!(JsonSearch::from_str(r#"$["a:string"].b:text"#).unwrap(), JsonSearch {
+            parts: vec![SearchPart::Key("a:string".to_string()), SearchPart::Key("b:text".to_string())],
+            optional: false,
+            lenient: false,
+        });
+    }
+
+    #[test]
+    fn type_guards_only_match_values_of_the_given_type() {
+        let target_value = json!({
+            "labels": { "a": "x", "b": 10, "c": "y", "d": null },
+            "items": [{ "a": 1 }, [1, 2], "text", { "b": 2 }, true],
+        });
+
+        let resolve = |search: &str| JsonSearch::from_str(search).unwrap().resolve(&target_value);
+
+        assert_eq!(resolve("$.labels.*:string"), Ok(vec![
+            JsonPath::from(["labels", "a"]),
+            JsonPath::from(["labels", "c"]),
+        ]));
+
+        assert_eq!(resolve("$.items.*{}"), Ok(vec![
+            JsonPath::from(["items", "0"]),
+            JsonPath::from(["items", "3"]),
+        ]));
+
+        assert_eq!(resolve("$.items.*[].*"), Ok(vec![
+            JsonPath::from(["items", "1", "0"]),
+            JsonPath::from(["items", "1", "1"]),
+        ]));
+
+        assert_eq!(resolve("$.labels.d:null"), Ok(vec![
+            JsonPath::from(["labels", "d"]),
+        ]));
+
+        assert_eq!(resolve("$.items:object"), Ok(vec![]));
+        assert_eq!(resolve("$:object.items.4:boolean"), Ok(vec![JsonPath::from(["items", "4"])]));
+    }
+
+    #[test]
+    fn rfc9535_queries_are_parsed_into_optional_searches() {
+        let search = JsonSearch::parse("$.store..book[0, -1:]['title']", SearchSyntax::Rfc9535).unwrap();
+
+        assert_eq!(search, JsonSearch {
+            parts: vec![
+                SearchPart::Key("store".to_string()),
+                SearchPart::RecursiveDescent,
+                SearchPart::Key("book".to_string()),
+                SearchPart::Union(vec![SearchPart::Index(0), SearchPart::Slice(ArraySlice::new(Some(-1), None, None))]),
+                SearchPart::Key("title".to_string()),
+            ],
+            optional: true,
+            lenient: true,
+        });
+
+        assert_eq!(search.to_string(), "~.store.**.book[0,-1:].title");
+        assert_eq!(JsonSearch::from_str(&search.to_string()), Ok(search));
+
+        assert_eq!(JsonSearch::parse("$.a.*", SearchSyntax::Native), JsonSearch::from_str("$.a.*"));
+    }
+
+    #[test]
+    fn lenient_searches_keep_skipping_mismatched_types_after_a_round_trip() {
+        let target_value = json!({ "o": { "x": 1 } });
+        let search = JsonSearch::parse("$.o.x.y", SearchSyntax::Rfc9535).unwrap();
+
+        assert_eq!(search.to_string(), "~.o.x.y");
+        assert_eq!(search.resolve(&target_value), Ok(vec![]));
+
+        let parsed = JsonSearch::from_str(&search.to_string()).unwrap();
+        assert_eq!(parsed, search);
+        assert_eq!(parsed.resolve(&target_value), Ok(vec![]));
+
+        assert_eq!(JsonSearch::from_str("?.o.x.y").unwrap().resolve(&target_value), Err(JsonSearchResolveError::NotAnObject(JsonPath::from(["o", "x"]))));
+        assert_eq!(JsonSearch::from_str("~.o.x.y | @.z").unwrap().resolve(&target_value), Err(JsonSearchResolveError::NotAnObject(JsonPath::from(["o", "x"]))));
+    }
+
+    #[test]
+    fn rfc9535_rejects_extensions_of_the_native_syntax() {
+        let parse = |s: &str| JsonSearch::parse(s, SearchSyntax::Rfc9535);
+
+        assert_eq!(parse("$.a.0"), Err(JsonSearchParseError::InvalidJsonPath("$.a.0".to_string(), 4, "expected a member name or '*'".to_string())));
+        assert!(matches!(parse("?.a"), Err(JsonSearchParseError::InvalidJsonPath(_, 0, _))));
+        assert!(matches!(parse("$.**"), Err(JsonSearchParseError::InvalidJsonPath(_, _, _))));
+        assert!(matches!(parse("$.a?"), Err(JsonSearchParseError::InvalidJsonPath(_, _, _))));
+        assert!(matches!(parse("$.a:string"), Err(JsonSearchParseError::InvalidJsonPath(_, _, _))));
+        assert!(matches!(parse("$[?@.* == 1]"), Err(JsonSearchParseError::InvalidJsonPath(_, _, _))));
+    }
+
+    #[test]
+    fn rfc9535_searches_select_nothing_instead_of_failing() {
+        let target_value = json!({ "a": [1, { "b": 2 }], "c": "text" });
+        let resolve = |s: &str| JsonSearch::parse(s, SearchSyntax::Rfc9535).unwrap().resolve(&target_value);
+
+        assert_eq!(resolve("$.c.d"), Ok(vec![]));
+        assert_eq!(resolve("$.a[5]"), Ok(vec![]));
+        assert_eq!(resolve("$['c'][*]"), Ok(vec![]));
+        assert_eq!(resolve("$.a[?@.b == 2]"), Ok(vec![JsonPath::from(["a", "1"])]));
+    }
+
+    #[test]
+    fn optional_native_searches_still_report_mismatched_types() {
+        let target_value = json!({ "s": "text", "a": [1] });
+        let resolve = |s: &str| JsonSearch::from_str(s).unwrap().resolve(&target_value);
+
+        assert_eq!(resolve("?.s.x"), Err(JsonSearchResolveError::NotAnObject(JsonPath::from(["s"]))));
+        assert_eq!(resolve("?.s.0"), Err(JsonSearchResolveError::NotAnArray(JsonPath::from(["s"]))));
+        assert_eq!(resolve("?.s.*"), Err(JsonSearchResolveError::NotAnArrayOrObject(JsonPath::from(["s"]))));
+        assert_eq!(resolve("$.s?.x?"), Err(JsonSearchResolveError::NotAnObject(JsonPath::from(["s"]))));
+        assert_eq!(resolve("?.a.x"), Err(JsonSearchResolveError::NotAnObject(JsonPath::from(["a"]))));
+        assert_eq!(resolve("?.a.5"), Ok(vec![]));
+        assert_eq!(resolve("?.x.y"), Ok(vec![]));
+
+        let lenient = JsonSearch::parse("$.s.x", SearchSyntax::Rfc9535).unwrap();
+        assert_eq!(lenient.resolve(&target_value), Ok(vec![]));
+        assert_eq!(lenient.then(&JsonSearch::from_str("?.y").unwrap()).resolve(&target_value), Err(JsonSearchResolveError::NotAnObject(JsonPath::from(["s"]))));
+    }
+
+    #[test]
+    fn navigation_parts_are_parsed_correctly() {
+        assert_eq!(JsonSearch::from_str("$.items.*.sku.^.^^.^*").unwrap(), JsonSearch {
+            parts: vec![
+                SearchPart::Key("items".to_string()),
+                SearchPart::Wildcard,
+                SearchPart::Key("sku".to_string()),
+                SearchPart::Parent,
+                SearchPart::Ancestors,
+                SearchPart::Siblings,
+            ],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(JsonSearch::from_str(r#"$["^"]"#).unwrap().parts, vec![SearchPart::Key("^".to_string())]);
+        assert_eq!(JsonSearch::from_str("$.a.^?").unwrap().to_string(), "$.a.^?");
+    }
+
+    #[test]
+    fn parent_parts_move_back_up_to_the_containing_value() {
+        let target_value = json!({
+            "items": [
+                { "sku": "a-1", "price": 10 },
+                { "price": 20 },
+                { "sku": "b-2", "price": 30 },
+            ],
+        });
+
+        let resolve = |search: &str| JsonSearch::from_str(search).unwrap().resolve(&target_value);
+
+        assert_eq!(resolve("$.items.*.sku.^"), Ok(vec![
+            JsonPath::from(["items", "0"]),
+            JsonPath::from(["items", "2"]),
+        ]));
+
+        assert_eq!(resolve("$.items.*.sku.^.price"), Ok(vec![
+            JsonPath::from(["items", "0", "price"]),
+            JsonPath::from(["items", "2", "price"]),
+        ]));
+
+        assert_eq!(resolve("$.items.-1.price.^.^.^"), Ok(vec![JsonPath::default()]));
+        assert_eq!(resolve("$.^"), Err(JsonSearchResolveError::MissingParent(JsonPath::default())));
+        assert_eq!(resolve("?.^"), Ok(vec![]));
+
+        assert_eq!(resolve("$.items[?@.price.^.sku]"), Ok(vec![
+            JsonPath::from(["items", "0"]),
+            JsonPath::from(["items", "2"]),
+        ]));
+
+        assert_eq!(resolve("$.items[?@.^]"), Ok(vec![]));
+    }
+
+    #[test]
+    fn parents_that_cannot_be_found_again_return_an_err() {
+        let target_value = json!({ "a": { "b": 1 } });
+        let context = ResolveContext::new(&target_value, FunctionRegistry::empty());
+
+        let found = SearchMatch {
+            path: JsonPath::from(["gone", "b"]),
+            captures: Default::default(),
+        };
+
+        for part in [SearchPart::Parent, SearchPart::Ancestors, SearchPart::Siblings] {
+            let search = JsonSearch {
+                parts: vec![part],
+                optional: false,
+                lenient: false,
+            };
+
+            let step = Step::new(&target_value["a"]["b"], found.clone(), &search.parts, true);
+
+            assert_eq!(search.resolve_step(&context, step), Err(JsonSearchResolveError::FailedToResolveParent(JsonPath::from(["gone"]), JsonPathResolveError::MissingKey("gone".to_string()))));
+        }
+    }
+
+    #[test]
+    fn ancestor_and_sibling_parts_select_neighbouring_values() {
+        let target_value = json!({
+            "a": { "b": { "c": 1, "d": 2, "e": 3 } },
+            "list": [10, 20, 30],
+        });
+
+        let resolve = |search: &str| JsonSearch::from_str(search).unwrap().resolve(&target_value);
+
+        assert_eq!(resolve("$.a.b.c.^^"), Ok(vec![
+            JsonPath::from(["a", "b"]),
+            JsonPath::from(["a"]),
+            JsonPath::default(),
+        ]));
+
+        assert_eq!(resolve("$.a.b.d.^*"), Ok(vec![
+            JsonPath::from(["a", "b", "c"]),
+            JsonPath::from(["a", "b", "e"]),
+        ]));
+
+        assert_eq!(resolve("$.list.1.^*"), Ok(vec![
+            JsonPath::from(["list", "0"]),
+            JsonPath::from(["list", "2"]),
+        ]));
+
+        assert_eq!(resolve("$.list.-1.^*"), Ok(vec![
+            JsonPath::from(["list", "0"]),
+            JsonPath::from(["list", "1"]),
+        ]));
+
+        assert_eq!(resolve("$.**.c.^^.list"), Ok(vec![JsonPath::from(["list"])]));
+        assert_eq!(resolve("$.^^"), Ok(vec![]));
+        assert_eq!(resolve("$.^*"), Err(JsonSearchResolveError::MissingParent(JsonPath::default())));
+    }
+
+    #[test]
+    fn captures_bind_the_keys_and_indices_of_matches() {
+        let target_value = json!({
+            "tenants": {
+                "acme": {
+                    "users": [
+                        { "email": "ann@acme.test" },
+                        { "name": "bob" },
+                    ],
+                },
+                "initech": {
+                    "users": [
+                        { "email": "peter@initech.test" },
+                    ],
+                },
+            },
+        });
+
+        let search = JsonSearch::from_str("$.tenants.{tenant}.users.{user}.email?").unwrap();
+        assert_eq!(search.to_string(), "$.tenants.{tenant}.users.{user}.email?");
+
+        let matches = search.resolve_matches(&target_value).unwrap();
+        assert_eq!(matches.len(), 2);
+
+        assert_eq!(matches[0].path(), &JsonPath::from(["tenants", "acme", "users", "0", "email"]));
+        assert_eq!(matches[0].capture("tenant"), Some(&PathPart::Key("acme".to_string())));
+        assert_eq!(matches[0].capture("user"), Some(&PathPart::Index(0)));
+
+        assert_eq!(matches[1].path(), &JsonPath::from(["tenants", "initech", "users", "0", "email"]));
+        assert_eq!(matches[1].capture("tenant"), Some(&PathPart::Key("initech".to_string())));
+        assert_eq!(matches[1].captures().len(), 2);
+
+        let parents = JsonSearch::from_str("$.tenants.{tenant}.users.^").unwrap().resolve_matches(&target_value).unwrap();
+        assert_eq!(parents[1].path(), &JsonPath::from(["tenants", "initech"]));
+        assert_eq!(parents[1].capture("tenant"), Some(&PathPart::Key("initech".to_string())));
+
+        assert_eq!(JsonSearch::from_str("$.{a b}").unwrap(), JsonSearch::from(["{a b}"]));
+        assert_eq!(JsonSearch::from_str("$.tenants.acme.users.1.name.{x}").unwrap().resolve(&target_value), Err(JsonSearchResolveError::NotAnArrayOrObject(JsonPath::from(["tenants", "acme", "users", "1", "name"]))));
+    }
+
+    #[test]
+    fn array_elements_are_selected_by_their_fields() {
+        let target_value = json!({
+            "users": [
+                { "id": 42, "role": "admin", "email": "ann@example.test" },
+                { "id": 7, "role": "user", "email": "bob@example.test" },
+                { "id": 9, "role": "admin" },
+            ],
+        });
+
+        let search = JsonSearch::from_str("$.users.[id=42].email").unwrap();
+        assert_eq!(search, JsonSearch {
+            parts: vec![
+                SearchPart::Key("users".to_string()),
+                SearchPart::Element(ElementKey::new("id", 42)),
+                SearchPart::Key("email".to_string()),
+            ],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(search.to_string(), "$.users[id=42].email");
+        assert_eq!(search.resolve(&target_value), Ok(vec![JsonPath::from(["users", "0", "email"])]));
+
+        assert_eq!(JsonSearch::from_str("$.users[role='admin'].email?").unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from(["users", "0", "email"]),
+        ]));
+
+        assert_eq!(JsonSearch::from_str("$.users[id=7,id=9]").unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from(["users", "1"]),
+            JsonPath::from(["users", "2"]),
+        ]));
+
+        assert_eq!(JsonSearch::from_str("$.users[id=1]").unwrap().resolve(&target_value), Err(JsonSearchResolveError::MissingRequiredElement(JsonPath::from(["users"]), ElementKey::new("id", 1))));
+        assert_eq!(JsonSearch::from_str("$.users[id=1]?").unwrap().resolve(&target_value), Ok(vec![]));
+        assert_eq!(JsonSearch::from_str("$.users[id=admin]"), Err(JsonSearchParseError::InvalidElementKey("id=admin".to_string())));
+    }
+
+    #[test]
+    fn bounded_recursive_descent_only_visits_the_given_levels() {
+        let target_value = json!({
+            "name": "root",
+            "a": {
+                "name": "a",
+                "b": {
+                    "name": "b",
+                    "c": { "name": "c" },
+                },
+            },
+        });
+
+        let search = JsonSearch::from_str("$.**{1,2}.name").unwrap();
+        assert_eq!(search, JsonSearch {
+            parts: vec![
+                SearchPart::BoundedDescent(DepthRange::new(1, Some(2))),
+                SearchPart::Key("name".to_string()),
+            ],
+            optional: false,
+            lenient: false,
+        });
+
+        assert_eq!(search.to_string(), "$.**{1,2}.name");
+
+        let resolve = |s: &str| JsonSearch::from_str(s).unwrap().resolve(&target_value);
+
+        assert_eq!(resolve("$.**{1,2}.name?"), Ok(vec![
+            JsonPath::from(["a", "name"]),
+            JsonPath::from(["a", "b", "name"]),
+        ]));
+
+        assert_eq!(resolve("$.**{,1}.name?"), Ok(vec![
+            JsonPath::from(["name"]),
+            JsonPath::from(["a", "name"]),
+        ]));
+
+        assert_eq!(resolve("$.**{3,}.name?"), Ok(vec![JsonPath::from(["a", "b", "c", "name"])]));
+        assert_eq!(resolve("$.**{2}:object"), Ok(vec![JsonPath::from(["a", "b"])]));
+        assert_eq!(resolve("$.**{1,2}.name"), resolve("$.**{1,2}.name?"));
+        assert!(matches!(JsonSearch::from_str("$.**{2,1}.name"), Err(JsonSearchParseError::InvalidDepthRange(_))));
+        assert!(matches!(JsonSearch::from_str("$[a,**{1}]"), Err(JsonSearchParseError::InvalidUnionMember(_))));
+    }
+
+    #[test]
+    fn exclusions_select_every_key_except_the_listed_ones() {
+        let target_value = json!({
+            "user": {
+                "name": "ann",
+                "email": "ann@example.test",
+                "password": "hunter2",
+            },
+            "config": {
+                "region": "eu",
+                "secret": "s3cret",
+                "token": "abc",
+                "api.key": "def",
+            },
+        });
+
+        assert_eq!(JsonSearch::from_str("$.config.*!{secret, token}").unwrap(), JsonSearch {
+            parts: vec![
+                SearchPart::Key("config".to_string()),
+                SearchPart::Exclusion(vec!["secret".to_string(), "token".to_string()]),
+            ],
+            optional: false,
+            lenient: false,
+        });
+
+        let resolve = |s: &str| JsonSearch::from_str(s).unwrap().resolve(&target_value);
+
+        assert_eq!(resolve("$.user.!password"), Ok(vec![
+            JsonPath::from(["user", "email"]),
+            JsonPath::from(["user", "name"]),
+        ]));
+
+        assert_eq!(resolve(r#"$.config.!{secret,token,"api.key"}"#), Ok(vec![
+            JsonPath::from(["config", "region"]),
+        ]));
+
+        assert_eq!(resolve("$.user.*!password"), resolve("$.user.!password"));
+        assert_eq!(resolve("$[?count(@.!password) == 2 && @.name != 1]"), Ok(vec![JsonPath::from(["user"])]));
+        assert_eq!(resolve("$.user.name.!password"), Err(JsonSearchResolveError::NotAnObject(JsonPath::from(["user", "name"]))));
+        assert_eq!(JsonSearch::from_str(r#"$.config.!{secret,"api.key"}"#).unwrap().to_string(), r#"$.config.!{secret,"api.key"}"#);
+        assert_eq!(JsonSearch::from_str("$.config.*!token").unwrap().to_string(), "$.config.!token");
+        assert!(matches!(JsonSearch::from_str("$.config.!{secret,}"), Err(JsonSearchParseError::InvalidExclusion(_))));
+    }
+
+    #[test]
+    fn searches_are_chained_relative_to_every_match() {
+        let target_value = json!({
+            "orders": [
+                { "lines": [{ "sku": "a-1" }, { "sku": "b|2" }] },
+                { "lines": [{ "sku": "c-3" }, { "qty": 1 }] },
+            ],
+        });
+
+        let orders = JsonSearch::from_str("$.orders.*").unwrap();
+        let lines = JsonSearch::from_str("$.lines.*").unwrap();
+        let skus = JsonSearch::from_str("?.sku").unwrap();
+
+        let piped = JsonSearch::from_str("$.orders.* | @.lines.* | @.sku").unwrap();
+        assert_eq!(piped, orders.then(&lines).then(&JsonSearch::from_str("$.sku").unwrap()));
+        assert_eq!(piped.to_string(), "$.orders.*.lines.*.sku");
+        assert_eq!(JsonSearch::from_str("$.orders.1 | @.lines.1 | @.sku").unwrap().resolve(&target_value), Err(JsonSearchResolveError::MissingRequiredKey(JsonPath::from(["orders", "1", "lines", "1"]), "sku".to_string())));
+
+        let chained = orders.then(&lines).then(&skus);
+        assert_eq!(chained.to_string(), "$.orders.*.lines.*.sku?");
+        assert_eq!(JsonSearch::from_str("?.orders.1").unwrap().then(&lines).to_string(), "$.orders?.1?.lines.*");
+        assert_eq!(chained.resolve(&target_value), Ok(vec![
+            JsonPath::from(["orders", "0", "lines", "0", "sku"]),
+            JsonPath::from(["orders", "0", "lines", "1", "sku"]),
+            JsonPath::from(["orders", "1", "lines", "0", "sku"]),
+        ]));
+
+        assert_eq!(JsonSearch::from_str(r#"$.orders.*.lines[?@.sku == "b|2"] | @.sku"#).unwrap().resolve(&target_value), Ok(vec![
+            JsonPath::from(["orders", "0", "lines", "1", "sku"]),
+        ]));
+
+        assert_eq!(JsonSearch::from_str("$./a|b/ | @.c").unwrap().to_string(), "$./a|b/.c");
+        assert_eq!(JsonSearch::from_str("$.o./x|@y/").unwrap().to_string(), "$.o./x|@y/");
+        assert_eq!(JsonSearch::from_str("$.o./x|@y/ | @.c").unwrap().to_string(), "$.o./x|@y/.c");
+        assert_eq!(JsonSearch::from_str("$.o./x|@y/").unwrap().resolve(&json!({ "o": { "@y": 1, "z": 2 } })), Ok(vec![JsonPath::from(["o", "@y"])]));
+        assert_eq!(JsonSearch::from_str("$.orders.* | @x"), Err(JsonSearchParseError::IncorrectStageRoot("@x".to_string())));
+    }
+
+    #[test]
+    fn matches_are_resolved_together_with_their_values() {
+        let target_value = json!({
+            "users": [
+                { "name": "Ada", "roles": ["admin"] },
+                { "name": "Brian" },
+            ],
+        });
+
+        assert_eq!(JsonSearch::from_str("$.users.*.name").unwrap().resolve_values(&target_value), Ok(vec![
+            (JsonPath::from(["users", "0", "name"]), &json!("Ada")),
+            (JsonPath::from(["users", "1", "name"]), &json!("Brian")),
+        ]));
+
+        assert_eq!(JsonSearch::from_str("$.users.0.roles.0.^.^").unwrap().resolve_values(&target_value), Ok(vec![
+            (JsonPath::from(["users", "0"]), &target_value["users"][0]),
+        ]));
+
+        assert_eq!(JsonSearch::from_str("$.users.1.roles").unwrap().resolve_values(&target_value), Err(JsonSearchResolveError::MissingRequiredKey(JsonPath::from(["users", "1"]), "roles".to_string())));
+
+        let search = JsonSearch::from_str("$.**.name").unwrap();
+        let values = search.resolve_values(&target_value).unwrap();
+
+        assert_eq!(values.iter().map(|(path, _)| path.clone()).collect::<Vec<_>>(), search.resolve(&target_value).unwrap());
+        assert!(values.iter().all(|(path, value)| path.resolve(&target_value) == Ok(*value)));
+    }
+
+    #[test]
+    fn matches_are_edited_in_bulk() {
+        let mut target_value = json!({
+            "users": [
+                { "name": "Ada", "password": "hunter2", "logins": 3 },
+                { "name": "Brian", "password": "letmein", "logins": 0 },
+            ],
+        });
+
+        let passwords = JsonSearch::from_str("$.users.*.password").unwrap();
+        assert_eq!(passwords.set_all(&mut target_value, json!("***")), Ok(2));
+
+        let logins = JsonSearch::from_str("$.users.*.logins").unwrap();
+        assert_eq!(logins.map_all(&mut target_value, |value| json!(value.as_i64().unwrap_or_default() + 1)), Ok(2));
+
+        let names = JsonSearch::from_str("$.users.[0,0,1].name").unwrap();
+        assert_eq!(names.update_all(&mut target_value, |value| *value = json!(value.as_str().unwrap().to_uppercase())), Ok(2));
+
+        assert_eq!(target_value, json!({
+            "users": [
+                { "name": "ADA", "password": "***", "logins": 4 },
+                { "name": "BRIAN", "password": "***", "logins": 1 },
+            ],
+        }));
+
+        let mut visited = vec![];
+        let everything = JsonSearch::from_str("$.users.**").unwrap();
+
+        assert_eq!(everything.for_each_mut(&mut target_value, |path, value| {
+            visited.push(path.clone());
+
+            if value.is_object() {
+                *value = json!(null);
+            }
+        }), Ok(9));
+
+        assert_eq!(visited.first(), Some(&JsonPath::from(["users", "0", "logins"])));
+        assert_eq!(visited.last(), Some(&JsonPath::from(["users"])));
+        assert_eq!(target_value, json!({ "users": [null, null] }));
+
+        assert_eq!(JsonSearch::from_str("$.groups.*").unwrap().set_all(&mut target_value, json!(1)), Err(JsonSearchResolveError::MissingRequiredKey(JsonPath::default(), "groups".to_string())));
+    }
+
+    #[test]
+    fn edits_leave_out_failed_branches_like_resolve() {
+        let original = json!({
+            "a": { "x": [1, 2, 3, 4, 5, 6] },
+            "b": { "x": [7] },
+            "c": 8,
+        });
+
+        let mut target_value = original.clone();
+        let mut visited = vec![];
+
+        assert_eq!(JsonSearch::from_str("$.*.x.[0,5]").unwrap().for_each_mut(&mut target_value, |path, value| {
+            visited.push(path.to_string());
+            *value = json!(0);
+        }), Ok(2));
+
+        assert_eq!(visited, vec!["$.a.x.0", "$.a.x.5"]);
+        assert_eq!(target_value["a"]["x"], json!([0, 2, 3, 4, 5, 0]));
+        assert_eq!(target_value["b"]["x"], json!([7]));
+
+        // `$.b.x.0.y` is reached before `$.b.x.1.y` turns out to be missing.
+        let mut target_value = json!({
+            "a": { "x": [{ "y": 1 }, { "y": 2 }] },
+            "b": { "x": [{ "y": 3 }, {}] },
+        });
+
+        assert_eq!(JsonSearch::from_str("$.*.x.[0,1].y").unwrap().set_all(&mut target_value, json!(0)), Ok(2));
+        assert_eq!(target_value, json!({
+            "a": { "x": [{ "y": 0 }, { "y": 0 }] },
+            "b": { "x": [{ "y": 3 }, {}] },
+        }));
+
+        // The matches in `$.a` are found before the search fails at `$.b`, but are not edited.
+        let mut target_value = original.clone();
+        let search = JsonSearch::from_str("$.[a,b].x.[0,1]").unwrap();
+
+        assert_eq!(search.set_all(&mut target_value, json!(0)), Err(JsonSearchResolveError::MissingRequiredIndex(JsonPath::from(["b", "x"]), 1)));
+        assert_eq!(target_value, original);
+    }
+
+    #[test]
+    fn matches_are_edited_like_after_resolving_the_search() {
+        // Searches that only move down are edited in a single walk, which should visit the same
+        // values in the same order as following the resolved paths.
+        fn agrees(segments: Vec<u8>, root: u8) -> bool {
+            let names = ["a", "b", "c", "0", "1", "-1", "*", "**", "**{1}", "[0,5]", "[0:2]", "c?", "[?@.a]", "[?@ > 1]", "{k}", "!a", "[b,d]", "[b,-1]", "[a=1]", "<object>"];
+            let built = segments.iter()
+                .take(6)
+                .map(|i| names[*i as usize % names.len()])
+                .fold(["$", "?", "~"][root as usize % 3].to_string(), |search, segment| search + "." + segment);
+
+            let search = JsonSearch::from_str(&built).unwrap();
+            let target_value = json!({
+                "a": { "b": [1, { "c": "x", "0": null }], "-1": [true, [2, 3]] },
+                "b": [{ "a": 1 }, "e", { "b": { "c": [] } }],
+                "c": { "a": { "a": 1.5 } },
+            });
+
+            let edit = |walk: bool| {
+                let mut target = target_value.clone();
+                let mut visited = vec![];
+                let mut f = |path: &JsonPath, value: &mut Value| {
+                    visited.push(path.clone());
+                    *value = json!(path.to_string());
+                };
+
+                let edited = match walk {
+                    true => EditWalk::new(&search, FunctionRegistry::empty(), &mut f).run(&mut target),
+                    false => search.for_each_mut_resolved(&mut target, FunctionRegistry::empty(), &mut f).ok(),
+                };
+
+                (edited, visited, target)
+            };
+
+            edit(true) == edit(false)
+        }
+
+        quickcheck(agrees as fn(Vec<u8>, u8) -> bool);
+    }
+
+    #[test]
+    fn keys_can_be_selected_and_filtered() {
+        let target_value = json!({
+            "settings": {
+                "password": "hunter2",
+                "passphrase": "correct horse",
+                "theme": "dark",
+            },
+            "tags": ["a", "b", "c"],
+        });
+
+        let search = JsonSearch::from_str("$.settings.*").unwrap();
+        assert_eq!(search.resolve_keys(&target_value), Ok(vec![
+            PathPart::Key("passphrase".to_string()),
+            PathPart::Key("password".to_string()),
+            PathPart::Key("theme".to_string()),
+        ]));
+
+        assert_eq!(JsonSearch::from_str("$.tags.1").unwrap().resolve_keys(&target_value), Ok(vec![PathPart::Index(1)]));
+        assert_eq!(JsonSearch::from_str("$").unwrap().resolve_keys(&target_value), Ok(vec![]));
+
+        let resolve = |s: &str| JsonSearch::from_str(s).unwrap().resolve(&target_value);
+
+        assert_eq!(resolve(r#"$.settings[?@~ == "password"]"#), Ok(vec![
+            JsonPath::from(["settings", "password"]),
+        ]));
+
+        assert_eq!(resolve(r#"$.settings[?match(@~, "pass.*")]"#), Ok(vec![
+            JsonPath::from(["settings", "passphrase"]),
+            JsonPath::from(["settings", "password"]),
+        ]));
+
+        assert_eq!(resolve("$.tags[?@~ >= 1]"), Ok(vec![
+            JsonPath::from(["tags", "1"]),
+            JsonPath::from(["tags", "2"]),
+        ]));
+
+        assert!(matches!(JsonSearch::from_str("$[?@~]"), Err(JsonSearchParseError::InvalidFilter(_, _))));
+        assert!(JsonSearch::parse("$[?@~ == 'a']", SearchSyntax::Rfc9535).is_err());
+    }
+
     #[test]
     fn required_search_returns_an_err_when_a_path_does_not_exist() {
         assert_eq!(JsonSearch::from(["b"]).resolve(&json!({ "a": 10 })), Err(JsonSearchResolveError::MissingRequiredKey(JsonPath::default(), "b".to_string())));
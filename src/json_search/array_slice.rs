@@ -0,0 +1,175 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::json_search::JsonSearchParseError;
+
+/// A `start:end:step` selection of array indices, following the slice semantics of RFC 9535.
+/// Omitted bounds default to the start or end of the array depending on the direction of the
+/// step, and negative bounds count from the end of the array.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ArraySlice {
+    pub start: Option<isize>,
+    pub end: Option<isize>,
+    pub step: Option<isize>,
+}
+
+impl ArraySlice {
+    pub fn new(start: Option<isize>, end: Option<isize>, step: Option<isize>) -> Self {
+        Self {
+            start,
+            end,
+            step,
+        }
+    }
+
+    /// Returns the indices selected by the slice for an array of the given length, in the order
+    /// in which they should be visited.
+    pub fn indices(&self, len: usize) -> Vec<usize> {
+        self.iter(len).collect()
+    }
+
+    /// Returns an iterator over the indices selected by the slice for an array of the given
+    /// length, which computes every index when it is needed.
+    pub fn iter(&self, len: usize) -> SliceIndices {
+        let len = len as isize;
+        let step = self.step.unwrap_or(1);
+
+        let normalize = |index: isize| if index >= 0 { index } else { len + index };
+
+        if step > 0 {
+            SliceIndices {
+                next: Some(normalize(self.start.unwrap_or(0)).clamp(0, len)),
+                bound: normalize(self.end.unwrap_or(len)).clamp(0, len),
+                step,
+            }
+        } else {
+            SliceIndices {
+                next: Some(normalize(self.start.unwrap_or(len - 1)).clamp(-1, len - 1)),
+                bound: normalize(self.end.unwrap_or(-len - 1)).clamp(-1, len - 1),
+                step,
+            }
+        }
+    }
+}
+
+/// Iterator over the indices selected by a slice, returned by [ArraySlice::iter].
+#[derive(Debug, Clone)]
+pub struct SliceIndices {
+    next: Option<isize>,
+    bound: isize,
+    step: isize,
+}
+
+impl Iterator for SliceIndices {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.next.filter(|index| match self.step {
+            0 => false,
+            step if step > 0 => *index < self.bound,
+            _ => self.bound < *index,
+        })?;
+
+        // Stepping past the largest or smallest index ends the slice instead of overflowing.
+        self.next = index.checked_add(self.step);
+
+        Some(index as usize)
+    }
+}
+
+impl FromStr for ArraySlice {
+    type Err = JsonSearchParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bounds: Vec<&str> = s.split(':').collect();
+
+        if bounds.len() < 2 || bounds.len() > 3 {
+            return Err(JsonSearchParseError::InvalidSlice(s.to_string()));
+        }
+
+        let parse_bound = |bound: &str| -> Result<Option<isize>, JsonSearchParseError> {
+            let bound = bound.trim();
+
+            if bound.is_empty() {
+                return Ok(None);
+            }
+
+            bound.parse()
+                .map(Some)
+                .map_err(|_| JsonSearchParseError::InvalidSlice(s.to_string()))
+        };
+
+        Ok(ArraySlice {
+            start: parse_bound(bounds[0])?,
+            end: parse_bound(bounds[1])?,
+            step: match bounds.get(2) {
+                Some(step) => parse_bound(step)?,
+                None => None,
+            },
+        })
+    }
+}
+
+impl Display for ArraySlice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(start) = self.start {
+            write!(f, "{}", start)?;
+        }
+
+        write!(f, ":")?;
+
+        if let Some(end) = self.end {
+            write!(f, "{}", end)?;
+        }
+
+        if let Some(step) = self.step {
+            write!(f, ":{}", step)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use crate::json_search::array_slice::ArraySlice;
+    use crate::json_search::JsonSearchParseError;
+
+    #[test]
+    fn slices_are_parsed_correctly() {
+        assert_eq!(ArraySlice::from_str("1:10:2"), Ok(ArraySlice::new(Some(1), Some(10), Some(2))));
+        assert_eq!(ArraySlice::from_str(":5"), Ok(ArraySlice::new(None, Some(5), None)));
+        assert_eq!(ArraySlice::from_str("::-1"), Ok(ArraySlice::new(None, None, Some(-1))));
+        assert_eq!(ArraySlice::from_str("-2:"), Ok(ArraySlice::new(Some(-2), None, None)));
+        assert_eq!(ArraySlice::from_str("1"), Err(JsonSearchParseError::InvalidSlice("1".to_string())));
+        assert_eq!(ArraySlice::from_str("a:b"), Err(JsonSearchParseError::InvalidSlice("a:b".to_string())));
+    }
+
+    #[test]
+    fn slices_are_displayed_correctly() {
+        assert_eq!(ArraySlice::new(Some(1), Some(10), Some(2)).to_string(), "1:10:2");
+        assert_eq!(ArraySlice::new(None, Some(5), None).to_string(), ":5");
+        assert_eq!(ArraySlice::new(None, None, Some(-1)).to_string(), "::-1");
+    }
+
+    #[test]
+    fn indices_are_selected_correctly() {
+        assert_eq!(ArraySlice::new(Some(1), Some(10), Some(2)).indices(6), vec![1, 3, 5]);
+        assert_eq!(ArraySlice::new(None, Some(3), None).indices(6), vec![0, 1, 2]);
+        assert_eq!(ArraySlice::new(Some(-2), None, None).indices(6), vec![4, 5]);
+        assert_eq!(ArraySlice::new(None, None, Some(-1)).indices(4), vec![3, 2, 1, 0]);
+        assert_eq!(ArraySlice::new(Some(4), Some(1), Some(-2)).indices(6), vec![4, 2]);
+        assert_eq!(ArraySlice::new(None, None, Some(0)).indices(6), Vec::<usize>::new());
+        assert_eq!(ArraySlice::new(Some(10), Some(20), None).indices(6), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn extreme_bounds_and_steps_do_not_overflow() {
+        assert_eq!(ArraySlice::new(Some(1), Some(2), Some(isize::MAX)).indices(6), vec![1]);
+        assert_eq!(ArraySlice::new(Some(isize::MIN), Some(isize::MAX), Some(isize::MAX)).indices(6), vec![0]);
+        assert_eq!(ArraySlice::new(Some(isize::MAX), Some(isize::MIN), Some(isize::MIN)).indices(6), vec![5]);
+        assert_eq!(ArraySlice::new(Some(isize::MIN), Some(isize::MAX), Some(isize::MIN)).indices(6), Vec::<usize>::new());
+        assert_eq!(ArraySlice::new(Some(isize::MAX), Some(isize::MIN), Some(-1)).indices(3), vec![2, 1, 0]);
+        assert_eq!(ArraySlice::new(Some(isize::MIN), Some(isize::MAX), None).indices(3), vec![0, 1, 2]);
+    }
+}
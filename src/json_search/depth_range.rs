@@ -0,0 +1,96 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::json_search::JsonSearchParseError;
+
+/// The levels a bounded recursive descent like `**{1,3}` visits, where level 0 is the value the
+/// descent starts at and level 1 are its children. Written as `{min,max}`, `{min,}` without an
+/// upper bound, `{,max}` starting at level 0 or `{depth}` for a single level.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DepthRange {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl DepthRange {
+    pub fn new(min: usize, max: Option<usize>) -> Self {
+        Self {
+            min,
+            max,
+        }
+    }
+
+    pub fn contains(&self, depth: usize) -> bool {
+        depth >= self.min && self.max.is_none_or(|max| depth <= max)
+    }
+
+    /// Checks if values below the given level can still be in range.
+    pub fn allows_below(&self, depth: usize) -> bool {
+        self.max.is_none_or(|max| depth < max)
+    }
+}
+
+impl FromStr for DepthRange {
+    type Err = JsonSearchParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || JsonSearchParseError::InvalidDepthRange(s.to_string());
+
+        let inner = s.strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+            .ok_or_else(invalid)?;
+
+        let parse_bound = |bound: &str| match bound.trim() {
+            "" => Ok(None),
+            bound => bound.parse().map(Some).map_err(|_| invalid()),
+        };
+
+        let range = match inner.split_once(',') {
+            Some((min, max)) => DepthRange::new(parse_bound(min)?.unwrap_or(0), parse_bound(max)?),
+            None => {
+                let depth = parse_bound(inner)?.ok_or_else(invalid)?;
+                DepthRange::new(depth, Some(depth))
+            }
+        };
+
+        if range.max.is_some_and(|max| max < range.min) {
+            return Err(invalid());
+        }
+
+        Ok(range)
+    }
+}
+
+impl Display for DepthRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{{{}}}", max),
+            Some(max) => write!(f, "{{{},{}}}", self.min, max),
+            None => write!(f, "{{{},}}", self.min),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use crate::json_search::depth_range::DepthRange;
+    use crate::json_search::JsonSearchParseError;
+
+    #[test]
+    fn depth_ranges_are_parsed_correctly() {
+        assert_eq!(DepthRange::from_str("{1,3}"), Ok(DepthRange::new(1, Some(3))));
+        assert_eq!(DepthRange::from_str("{ 2 , }"), Ok(DepthRange::new(2, None)));
+        assert_eq!(DepthRange::from_str("{,3}"), Ok(DepthRange::new(0, Some(3))));
+        assert_eq!(DepthRange::from_str("{2}"), Ok(DepthRange::new(2, Some(2))));
+        assert_eq!(DepthRange::from_str("{3,1}"), Err(JsonSearchParseError::InvalidDepthRange("{3,1}".to_string())));
+        assert_eq!(DepthRange::from_str("{}"), Err(JsonSearchParseError::InvalidDepthRange("{}".to_string())));
+        assert_eq!(DepthRange::from_str("{-1,2}"), Err(JsonSearchParseError::InvalidDepthRange("{-1,2}".to_string())));
+    }
+
+    #[test]
+    fn depth_ranges_are_displayed_in_their_shortest_form() {
+        assert_eq!(DepthRange::new(1, Some(3)).to_string(), "{1,3}");
+        assert_eq!(DepthRange::new(2, Some(2)).to_string(), "{2}");
+        assert_eq!(DepthRange::new(0, None).to_string(), "{0,}");
+    }
+}
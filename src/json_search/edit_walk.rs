@@ -0,0 +1,375 @@
+use std::collections::VecDeque;
+use serde_json::Value;
+use crate::json_path::JsonPath;
+use crate::json_path::path_part::{normalize_index, PathPart};
+use crate::json_search::depth_range::DepthRange;
+use crate::json_search::expansion::{ChildFilter, Cursor};
+use crate::json_search::function_registry::FunctionRegistry;
+use crate::json_search::search_part::SearchPart;
+use crate::json_search::{JsonSearch, ResolveContext};
+
+/// Walk through a document that edits the matches of a search while resolving it, used by
+/// [JsonSearch::for_each_mut] for searches that only move down and whose filters are
+/// self-contained. Every value is visited at most once, and matches are edited on the way back
+/// up, so values nested inside a match are edited before it.
+///
+/// Like with [JsonSearch::resolve], a failure leaves out the matches of the nearest branch it
+/// passes, and fails the whole search when it passes no branch. A match is therefore only edited
+/// once none of its branches can fail anymore, and waits together with every match after it
+/// until then, so the matches are still edited in order.
+pub(crate) struct EditWalk<'s, F> {
+    search: &'s JsonSearch,
+    functions: &'s FunctionRegistry,
+    f: F,
+
+    /// Branches that can fail, where the first one is the search itself.
+    scopes: Vec<Scope>,
+
+    /// Matches that cannot be edited yet, in the order they should be edited, each with the
+    /// scopes of the steps that matched it.
+    waiting: VecDeque<(JsonPath, Vec<usize>)>,
+    edited: usize,
+}
+
+/// A branch that can fail, which fails the branches it contains along with it.
+struct Scope {
+    parent: Option<usize>,
+    failed: bool,
+
+    /// The number of steps in this scope that still have to be applied and can fail it.
+    fallible: usize,
+}
+
+/// The parts that still have to be applied to a value, like [crate::json_search::Step].
+#[derive(Clone, Copy)]
+struct WalkStep<'s> {
+    part: Option<&'s SearchPart>,
+    rest: &'s [SearchPart],
+    optional: bool,
+    depth: usize,
+    branch: bool,
+    scope: usize,
+
+    /// Whether applying the remaining parts can fail the scope of this step.
+    fallible: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+    Failed,
+    Waiting,
+    Settled,
+}
+
+impl<'s, F> EditWalk<'s, F>
+    where
+        F: FnMut(&JsonPath, &mut Value),
+{
+    pub(crate) fn new(search: &'s JsonSearch, functions: &'s FunctionRegistry, f: F) -> Self {
+        EditWalk {
+            search,
+            functions,
+            f,
+            scopes: vec![Scope {
+                parent: None,
+                failed: false,
+                fallible: 0,
+            }],
+            waiting: VecDeque::new(),
+            edited: 0,
+        }
+    }
+
+    /// Edits every match, returning the number of edited values, or `None` when the search
+    /// fails. Nothing is edited in that case, as the search itself can fail until the end.
+    pub(crate) fn run(mut self, target: &mut Value) -> Option<usize> {
+        let parts = self.search.parts.as_slice();
+        let start = self.produce(0, parts.first(), parts.get(1..).unwrap_or_default(), self.search.optional, 0, false);
+
+        self.walk(target, &mut JsonPath::default(), vec![start])?;
+
+        Some(self.edited)
+    }
+
+    /// Applies the steps that reached the value, walks into the children they continue with and
+    /// then edits the value if it was matched.
+    fn walk(&mut self, value: &mut Value, path: &mut JsonPath, steps: Vec<WalkStep<'s>>) -> Option<()> {
+        let mut steps = VecDeque::from(steps);
+        let mut children = vec![];
+        let mut matches = vec![];
+
+        while let Some(step) = steps.pop_front() {
+            if step.fallible {
+                self.scopes[step.scope].fallible -= 1;
+            }
+
+            if self.status(step.scope) == Status::Failed {
+                continue;
+            }
+
+            match step.part {
+                Some(part) => self.apply(value, step, part, &mut steps, &mut children)?,
+                None => matches.push(step.scope),
+            }
+        }
+
+        children.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut children = children.into_iter().peekable();
+
+        while let Some((part, step)) = children.next() {
+            let mut group = vec![step];
+
+            while let Some((_, step)) = children.next_if(|(next, _)| *next == part) {
+                group.push(step);
+            }
+
+            let child = match (&mut *value, &part) {
+                (Value::Object(map), PathPart::Key(key)) => map.get_mut(key),
+                (Value::Array(array), PathPart::Index(index)) => array.get_mut(*index as usize),
+                _ => None,
+            };
+
+            if let Some(child) = child {
+                path.push(part);
+                self.walk(child, path, group)?;
+                path.pop();
+            }
+        }
+
+        self.finish(value, path, &matches);
+
+        Some(())
+    }
+
+    /// Applies the part of a step, adding the steps that continue at the same value to the steps
+    /// and those that continue at a child to the children.
+    fn apply(&mut self, value: &Value, step: WalkStep<'s>, part: &'s SearchPart, steps: &mut VecDeque<WalkStep<'s>>, children: &mut Vec<(PathPart, WalkStep<'s>)>) -> Option<()> {
+        let lenient = self.search.lenient;
+
+        match part {
+            SearchPart::Key(key) => match value {
+                Value::Object(map) if map.contains_key(key) => children.push((PathPart::Key(key.clone()), self.next(&step, false))),
+                Value::Object(_) => self.fail_unless(step.optional, step.scope)?,
+                _ => self.fail_unless(lenient, step.scope)?,
+            },
+            SearchPart::Index(index) => match value {
+                Value::Array(array) => match normalize_index(*index, array.len()) {
+                    Some(position) => children.push((PathPart::Index(position as isize), self.next(&step, false))),
+                    None => self.fail_unless(step.optional, step.scope)?,
+                },
+                _ => self.fail_unless(lenient, step.scope)?,
+            },
+            SearchPart::Wildcard | SearchPart::Capture(_) => self.children(value, &step, Cursor::new(value), ChildFilter::All, children)?,
+            SearchPart::Filter(expression) => self.children(value, &step, Cursor::new(value), ChildFilter::Filter(expression), children)?,
+            SearchPart::Slice(slice) => match value {
+                Value::Array(array) => self.children(value, &step, Cursor::Slice(array, slice.iter(array.len())), ChildFilter::All, children)?,
+                _ => self.fail_unless(lenient, step.scope)?,
+            },
+            SearchPart::Element(key) => match value {
+                Value::Array(array) if key.position(array).is_some() => self.children(value, &step, Cursor::new(value), ChildFilter::Element(key), children)?,
+                Value::Array(_) => self.fail_unless(step.optional, step.scope)?,
+                _ => self.fail_unless(lenient, step.scope)?,
+            },
+            SearchPart::KeyPattern(pattern) => match value {
+                Value::Object(_) => self.children(value, &step, Cursor::new(value), ChildFilter::KeyPattern(pattern), children)?,
+                _ => self.fail_unless(lenient, step.scope)?,
+            },
+            SearchPart::Exclusion(keys) => match value {
+                Value::Object(_) => self.children(value, &step, Cursor::new(value), ChildFilter::Exclusion(keys), children)?,
+                _ => self.fail_unless(lenient, step.scope)?,
+            },
+            SearchPart::RecursiveDescent => self.descend(value, &step, part, DepthRange::default(), steps, children),
+            SearchPart::BoundedDescent(range) => self.descend(value, &step, part, *range, steps, children),
+            SearchPart::TypeGuard(value_type) => {
+                if value_type.matches(value) {
+                    let next = self.next(&step, false);
+                    steps.push_back(next);
+                }
+            }
+            SearchPart::Union(members) => {
+                for member in members {
+                    let next = self.produce(step.scope, Some(member), step.rest, step.optional, step.depth, step.branch);
+                    steps.push_back(next);
+                }
+            }
+            SearchPart::Optional(inner) => {
+                let next = self.produce(step.scope, Some(inner), step.rest, true, step.depth, step.branch);
+                steps.push_back(next);
+            }
+
+            // Searches that move up are resolved before they are edited instead.
+            SearchPart::Parent | SearchPart::Ancestors | SearchPart::Siblings => return None,
+        }
+
+        Some(())
+    }
+
+    /// Continues at every child that is kept by the filter, as branches. Values that are not an
+    /// array or an object have no children, which fails the step unless the search is lenient.
+    fn children(&mut self, value: &Value, step: &WalkStep<'s>, cursor: Cursor, filter: ChildFilter, children: &mut Vec<(PathPart, WalkStep<'s>)>) -> Option<()> {
+        if !matches!(value, Value::Array(_) | Value::Object(_)) {
+            return self.fail_unless(self.search.lenient, step.scope);
+        }
+
+        let context = ResolveContext::new(value, self.functions);
+
+        for (part, child) in cursor {
+            if filter.keeps(&part, child, &context).ok()? {
+                let next = self.next(step, true);
+                children.push((part, next));
+            }
+        }
+
+        Some(())
+    }
+
+    /// Continues with the value itself when it lies within the range, and repeats the descent at
+    /// every child one level further down, all as branches.
+    fn descend(&mut self, value: &Value, step: &WalkStep<'s>, part: &'s SearchPart, range: DepthRange, steps: &mut VecDeque<WalkStep<'s>>, children: &mut Vec<(PathPart, WalkStep<'s>)>) {
+        if range.contains(step.depth) {
+            let next = self.next(step, true);
+            steps.push_back(next);
+        }
+
+        if range.allows_below(step.depth) {
+            for (child, _) in Cursor::new(value) {
+                let next = self.produce(step.scope, Some(part), step.rest, step.optional, step.depth + 1, true);
+                children.push((child, next));
+            }
+        }
+    }
+
+    /// Edits the value when it is matched by a step whose branches can no longer fail, and then
+    /// edits the waiting matches inside it that were settled in the meantime.
+    fn finish(&mut self, value: &mut Value, path: &JsonPath, matches: &[usize]) {
+        match self.best_status(matches) {
+            Some(Status::Settled) if self.waiting.is_empty() => {
+                (self.f)(path, value);
+                self.edited += 1;
+            }
+            Some(Status::Settled | Status::Waiting) => self.waiting.push_back((path.clone(), matches.to_vec())),
+            Some(Status::Failed) | None => {}
+        }
+
+        let mut settled = vec![];
+
+        while let Some((found, scopes)) = self.waiting.front() {
+            if !found.starts_with(path) {
+                break;
+            }
+
+            match self.best_status(scopes) {
+                Some(Status::Waiting) => break,
+                Some(Status::Settled) => settled.extend(self.waiting.pop_front().map(|(found, _)| found)),
+                Some(Status::Failed) | None => {
+                    self.waiting.pop_front();
+                }
+            }
+        }
+
+        settled.sort();
+
+        self.edited += JsonPath::for_each_mut(&settled, path, value, &mut self.f);
+    }
+
+    /// Continues with the parts after the current one.
+    fn next(&mut self, step: &WalkStep<'s>, branch: bool) -> WalkStep<'s> {
+        self.produce(step.scope, step.rest.first(), step.rest.get(1..).unwrap_or_default(), self.search.optional, 0, branch)
+    }
+
+    /// Creates a step, giving branches that can fail a scope of their own.
+    fn produce(&mut self, scope: usize, part: Option<&'s SearchPart>, rest: &'s [SearchPart], optional: bool, depth: usize, branch: bool) -> WalkStep<'s> {
+        let fallible = part.is_some_and(|part| self.can_fail(part, rest, optional));
+
+        let scope = match branch && fallible {
+            true => {
+                self.scopes.push(Scope {
+                    parent: Some(scope),
+                    failed: false,
+                    fallible: 0,
+                });
+
+                self.scopes.len() - 1
+            }
+            false => scope,
+        };
+
+        if fallible {
+            self.scopes[scope].fallible += 1;
+        }
+
+        WalkStep {
+            part,
+            rest,
+            optional,
+            depth,
+            branch,
+            scope,
+            fallible,
+        }
+    }
+
+    /// Checks if applying the part and the parts after it can fail the step, without counting
+    /// the failures of the branches it continues with.
+    fn can_fail(&self, part: &SearchPart, rest: &[SearchPart], optional: bool) -> bool {
+        let lenient = self.search.lenient;
+        let rest_can_fail = || rest.first().is_some_and(|next| self.can_fail(next, &rest[1..], self.search.optional));
+
+        match part {
+            SearchPart::Key(_) | SearchPart::Index(_) => !optional || !lenient || rest_can_fail(),
+            SearchPart::Element(_) => !optional || !lenient,
+            SearchPart::Wildcard | SearchPart::Capture(_) | SearchPart::Filter(_) | SearchPart::Slice(_) | SearchPart::KeyPattern(_) | SearchPart::Exclusion(_) => !lenient,
+            SearchPart::RecursiveDescent | SearchPart::BoundedDescent(_) => false,
+            SearchPart::TypeGuard(_) => rest_can_fail(),
+            SearchPart::Union(members) => members.iter().any(|member| self.can_fail(member, rest, optional)),
+            SearchPart::Optional(inner) => self.can_fail(inner, rest, true),
+            SearchPart::Parent | SearchPart::Ancestors | SearchPart::Siblings => true,
+        }
+    }
+
+    /// Fails the scope unless the failure should be skipped. A failure of the search itself ends
+    /// the walk.
+    fn fail_unless(&mut self, skip: bool, scope: usize) -> Option<()> {
+        if skip {
+            return Some(());
+        }
+
+        match scope {
+            0 => None,
+            _ => {
+                self.scopes[scope].failed = true;
+                Some(())
+            }
+        }
+    }
+
+    /// A match is left out when any of its scopes failed, and settled once none of them can fail
+    /// anymore.
+    fn status(&self, scope: usize) -> Status {
+        let mut status = Status::Settled;
+        let mut current = Some(scope);
+
+        while let Some(scope) = current {
+            if self.scopes[scope].failed {
+                return Status::Failed;
+            }
+
+            if self.scopes[scope].fallible > 0 {
+                status = Status::Waiting;
+            }
+
+            current = self.scopes[scope].parent;
+        }
+
+        status
+    }
+
+    /// The status of a value that was matched by steps of the scopes, where a single match that
+    /// is not left out is enough to edit it.
+    fn best_status(&self, scopes: &[usize]) -> Option<Status> {
+        scopes.iter()
+            .map(|scope| self.status(*scope))
+            .max()
+    }
+}
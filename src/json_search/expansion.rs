@@ -0,0 +1,185 @@
+use std::iter::Enumerate;
+use std::slice::Iter;
+use std::vec::IntoIter;
+use serde_json::map;
+use serde_json::Value;
+use crate::json_path::element_key::ElementKey;
+use crate::json_path::path_part::PathPart;
+use crate::json_search::array_slice::SliceIndices;
+use crate::json_search::depth_range::DepthRange;
+use crate::json_search::filter::{FilterContext, FilterExpression};
+use crate::json_search::key_pattern::KeyPattern;
+use crate::json_search::search_match::SearchMatch;
+use crate::json_search::{JsonSearch, JsonSearchResolveError, ResolveContext, Step};
+
+/// The steps that continue from a step once its part is applied. Children of arrays and objects
+/// are produced one at a time, so a wide array never turns into a list of steps.
+pub(crate) enum Expansion<'s, 'v> {
+    /// A few steps that are known up front, like the members of a union.
+    Steps(IntoIter<Step<'s, 'v>>),
+
+    /// The children of a value that are kept by the filter, continuing with the parts after the
+    /// part of the origin.
+    Children {
+        origin: Step<'s, 'v>,
+        parent: SearchMatch,
+        cursor: Cursor<'v>,
+        filter: ChildFilter<'s>,
+    },
+
+    /// The value of the origin itself when it lies within the range, followed by its children,
+    /// which repeat the descent one level further down.
+    Descent {
+        origin: Step<'s, 'v>,
+        range: DepthRange,
+        started: bool,
+        cursor: Cursor<'v>,
+    },
+}
+
+/// Position within the children of an array or an object.
+pub(crate) enum Cursor<'v> {
+    Array(Enumerate<Iter<'v, Value>>),
+    Object(map::Iter<'v>),
+    Slice(&'v [Value], SliceIndices),
+    Empty,
+}
+
+/// Decides which children of a value are kept.
+pub(crate) enum ChildFilter<'s> {
+    All,
+
+    /// Keeps every child like [ChildFilter::All], binding its key or index to the capture.
+    Capture(&'s str),
+    Element(&'s ElementKey),
+    Filter(&'s FilterExpression),
+    KeyPattern(&'s KeyPattern),
+    Exclusion(&'s [String]),
+
+    /// Keeps every child except the one stored under the part.
+    Except(PathPart),
+}
+
+impl<'s, 'v> Expansion<'s, 'v> {
+    pub(crate) fn single(step: Step<'s, 'v>) -> Self {
+        Expansion::Steps(vec![step].into_iter())
+    }
+
+    /// Produces the next step, or `None` when every step was produced. Only filters that call a
+    /// user-defined function can fail.
+    pub(crate) fn next(&mut self, search: &JsonSearch, context: &ResolveContext) -> Result<Option<Step<'s, 'v>>, JsonSearchResolveError> {
+        let step = match self {
+            Expansion::Steps(steps) => steps.next(),
+            Expansion::Children { origin, parent, cursor, filter } => {
+                for (part, value) in cursor.by_ref() {
+                    if !filter.keeps(&part, value, context)? {
+                        continue;
+                    }
+
+                    let mut found = parent.clone();
+
+                    if let ChildFilter::Capture(name) = filter {
+                        found.captures.insert(name.to_string(), part.clone());
+                    }
+
+                    found.push(part);
+
+                    return Ok(Some(search.next(origin, value, found, true)));
+                }
+
+                None
+            }
+            Expansion::Descent { origin, range, started, cursor } => {
+                if !*started {
+                    *started = true;
+
+                    if range.contains(origin.depth) {
+                        return Ok(Some(search.next(origin, origin.value, origin.found.clone(), true)));
+                    }
+                }
+
+                cursor.next().map(|(part, value)| {
+                    let mut found = origin.found.clone();
+                    found.push(part);
+
+                    Step {
+                        value,
+                        found,
+                        depth: origin.depth + 1,
+                        branch: true,
+                        ..*origin
+                    }
+                })
+            }
+        };
+
+        Ok(step)
+    }
+}
+
+impl Expansion<'_, '_> {
+    /// Checks if any of the steps that are still to be produced is not a branch, which means that
+    /// its failure would still fail the step this expansion came from.
+    pub(crate) fn has_required_steps(&self) -> bool {
+        match self {
+            Expansion::Steps(steps) => steps.as_slice().iter().any(|step| !step.branch),
+            Expansion::Children { .. } | Expansion::Descent { .. } => false,
+        }
+    }
+}
+
+impl Default for Expansion<'_, '_> {
+    fn default() -> Self {
+        Expansion::Steps(vec![].into_iter())
+    }
+}
+
+impl<'v> Cursor<'v> {
+    /// Starts before the first child of the value. Values that are not an array or an object
+    /// have no children.
+    pub(crate) fn new(value: &'v Value) -> Self {
+        match value {
+            Value::Array(array) => Cursor::Array(array.iter().enumerate()),
+            Value::Object(map) => Cursor::Object(map.iter()),
+            _ => Cursor::Empty,
+        }
+    }
+}
+
+impl<'v> Iterator for Cursor<'v> {
+    type Item = (PathPart, &'v Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Cursor::Array(children) => children.next()
+                .map(|(i, value)| (PathPart::Index(i as isize), value)),
+            Cursor::Object(children) => children.next()
+                .map(|(key, value)| (PathPart::Key(key.to_string()), value)),
+            Cursor::Slice(array, indices) => indices.next()
+                .map(|i| (PathPart::Index(i as isize), &array[i])),
+            Cursor::Empty => None,
+        }
+    }
+}
+
+impl ChildFilter<'_> {
+    pub(crate) fn keeps(&self, part: &PathPart, value: &Value, context: &ResolveContext) -> Result<bool, JsonSearchResolveError> {
+        let keeps = match self {
+            ChildFilter::All | ChildFilter::Capture(_) => true,
+            ChildFilter::Element(key) => key.matches(value),
+            ChildFilter::Filter(expression) => {
+                let filter = FilterContext {
+                    key: Some(part),
+                    ..context.filter
+                };
+
+                expression.matches(value, &filter)?
+            }
+            ChildFilter::KeyPattern(pattern) => matches!(part, PathPart::Key(key) if pattern.matches(key)),
+            ChildFilter::Exclusion(keys) => !matches!(part, PathPart::Key(key) if keys.contains(key)),
+            ChildFilter::Except(current) => part != current,
+        };
+
+        Ok(keeps)
+    }
+}
@@ -0,0 +1,603 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde_json::{Number, Value};
+use crate::json_path::path_part::PathPart;
+use crate::json_search::{JsonSearch, JsonSearchParseError, JsonSearchResolveError};
+use crate::json_search::filter_function::{FunctionCall, FunctionType, FunctionValue};
+use crate::json_search::function_registry::FunctionRegistry;
+use crate::json_search::search_part::{parse_segments, SearchPart};
+use crate::syntax::{parse_string_literal, split_segments};
+
+/// A filter predicate in the style of RFC 9535, written as `[?<expression>]` inside a search.
+/// The expression is evaluated against every child of the current array or object, which is
+/// available as `@`, while `$` refers to the root of the document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterExpression {
+    Or(Box<FilterExpression>, Box<FilterExpression>),
+    And(Box<FilterExpression>, Box<FilterExpression>),
+    Not(Box<FilterExpression>),
+    Comparison(Comparable, ComparisonOperator, Comparable),
+    Exists(FilterQuery),
+
+    /// A call to a function returning a logical or nodes, used as a test like
+    /// `match(@.name, "web-[0-9]+")`.
+    Function(FunctionCall),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComparisonOperator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Comparable {
+    Literal(Value),
+    Query(FilterQuery),
+
+    /// A call to a function returning a value, like `length(@.roles)`.
+    Function(FunctionCall),
+
+    /// The key or index the current value is stored under, written as `@~` like
+    /// `$.settings[?@~ == "password"]`. Keys are strings and indices are numbers.
+    Key,
+}
+
+/// A query embedded in a filter expression, starting either at the current node (`@`) or at the
+/// root of the document (`$`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterQuery {
+    pub relative: bool,
+    pub parts: Vec<SearchPart>,
+}
+
+/// Everything besides the current value that a filter expression is evaluated against.
+#[derive(Debug, Clone, Copy)]
+pub struct FilterContext<'a> {
+    pub root: &'a Value,
+
+    /// The key or index the current value is stored under, if it is stored under anything.
+    pub key: Option<&'a PathPart>,
+
+    pub functions: &'a FunctionRegistry,
+}
+
+impl FilterExpression {
+    /// Checks if the current value matches the expression. Only calls to user-defined functions
+    /// can fail, when they cannot be called or return a result of the wrong type.
+    pub fn matches(&self, current: &Value, context: &FilterContext) -> Result<bool, JsonSearchResolveError> {
+        let matches = match self {
+            FilterExpression::Or(left, right) => left.matches(current, context)? || right.matches(current, context)?,
+            FilterExpression::And(left, right) => left.matches(current, context)? && right.matches(current, context)?,
+            FilterExpression::Not(inner) => !inner.matches(current, context)?,
+            FilterExpression::Comparison(left, operator, right) => {
+                operator.compare(left.evaluate(current, context)?.as_deref(), right.evaluate(current, context)?.as_deref())
+            }
+            FilterExpression::Exists(query) => !query.select(current, context)?.is_empty(),
+            FilterExpression::Function(call) => call.test(current, context)?,
+        };
+
+        Ok(matches)
+    }
+
+    /// Checks if the expression only depends on the value it is applied to and cannot fail, which
+    /// holds when none of its queries start at the root and it calls no user-defined functions.
+    pub(crate) fn is_self_contained(&self) -> bool {
+        match self {
+            FilterExpression::Or(left, right) | FilterExpression::And(left, right) => left.is_self_contained() && right.is_self_contained(),
+            FilterExpression::Not(inner) => inner.is_self_contained(),
+            FilterExpression::Comparison(left, _, right) => left.is_self_contained() && right.is_self_contained(),
+            FilterExpression::Exists(query) => query.is_self_contained(),
+            FilterExpression::Function(call) => call.is_self_contained(),
+        }
+    }
+}
+
+impl ComparisonOperator {
+    /// Compares two optional values, where `None` stands for a query that did not produce exactly
+    /// one value. Only numbers and strings are ordered; any other combination is never less than
+    /// or greater than another value.
+    pub fn compare(&self, left: Option<&Value>, right: Option<&Value>) -> bool {
+        match self {
+            ComparisonOperator::Equal => values_equal(left, right),
+            ComparisonOperator::NotEqual => !values_equal(left, right),
+            ComparisonOperator::Less => values_less(left, right),
+            ComparisonOperator::LessOrEqual => values_less(left, right) || values_equal(left, right),
+            ComparisonOperator::Greater => values_less(right, left),
+            ComparisonOperator::GreaterOrEqual => values_less(right, left) || values_equal(left, right),
+        }
+    }
+}
+
+impl Comparable {
+    pub(crate) fn evaluate<'a>(&'a self, current: &'a Value, context: &FilterContext<'a>) -> Result<Option<Cow<'a, Value>>, JsonSearchResolveError> {
+        let value = match self {
+            Comparable::Literal(value) => Some(Cow::Borrowed(value)),
+            Comparable::Query(query) => {
+                let mut nodes = query.select(current, context)?;
+
+                match nodes.len() {
+                    1 => nodes.pop().map(Cow::Borrowed),
+                    _ => None,
+                }
+            }
+            Comparable::Function(call) => match call.evaluate(current, context)? {
+                FunctionValue::Value(value) => value,
+                _ => None,
+            },
+            Comparable::Key => context.key.map(|key| match key {
+                PathPart::Key(key) => Cow::Owned(Value::from(key.as_str())),
+                PathPart::Index(index) => Cow::Owned(Value::from(*index)),
+                PathPart::Element(key) => Cow::Owned(Value::from(key.to_string())),
+            }),
+        };
+
+        Ok(value)
+    }
+
+    pub(crate) fn is_self_contained(&self) -> bool {
+        match self {
+            Comparable::Literal(_) | Comparable::Key => true,
+            Comparable::Query(query) => query.is_self_contained(),
+            Comparable::Function(call) => call.is_self_contained(),
+        }
+    }
+}
+
+impl FilterQuery {
+    /// Returns the values selected by the query. Missing keys and mismatched types simply select
+    /// nothing instead of raising an error, while failed calls to user-defined functions in nested
+    /// filters are returned.
+    pub fn select<'a>(&self, current: &'a Value, context: &FilterContext<'a>) -> Result<Vec<&'a Value>, JsonSearchResolveError> {
+        let start = if self.relative { current } else { context.root };
+
+        JsonSearch::select(context, &self.parts, start)
+    }
+
+    /// Checks if the query can select at most one value, which is the case when it only consists
+    /// of keys and indices.
+    pub fn is_singular(&self) -> bool {
+        self.parts.iter().all(|part| matches!(part, SearchPart::Key(_) | SearchPart::Index(_)))
+    }
+
+    pub(crate) fn is_self_contained(&self) -> bool {
+        self.relative && self.parts.iter().all(SearchPart::is_self_contained)
+    }
+}
+
+fn values_equal(left: Option<&Value>, right: Option<&Value>) -> bool {
+    match (left, right) {
+        (None, None) => true,
+        (Some(left), Some(right)) => json_equal(left, right),
+        _ => false,
+    }
+}
+
+fn json_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => compare_numbers(left, right) == Some(Ordering::Equal),
+        (Value::Array(left), Value::Array(right)) => {
+            left.len() == right.len() && left.iter()
+                .zip(right)
+                .all(|(left, right)| json_equal(left, right))
+        }
+        (Value::Object(left), Value::Object(right)) => {
+            left.len() == right.len() && left.iter()
+                .all(|(key, value)| right.get(key).is_some_and(|other| json_equal(value, other)))
+        }
+        _ => left == right,
+    }
+}
+
+fn values_less(left: Option<&Value>, right: Option<&Value>) -> bool {
+    match (left, right) {
+        (Some(Value::Number(left)), Some(Value::Number(right))) => compare_numbers(left, right) == Some(Ordering::Less),
+        (Some(Value::String(left)), Some(Value::String(right))) => left < right,
+        _ => false,
+    }
+}
+
+/// Compares integers exactly, as converting them to floating point numbers loses precision above
+/// 2^53. Any other combination is compared as floating point numbers.
+fn compare_numbers(left: &Number, right: &Number) -> Option<Ordering> {
+    match (as_integer(left), as_integer(right)) {
+        (Some(left), Some(right)) => Some(left.cmp(&right)),
+        _ => left.as_f64().partial_cmp(&right.as_f64()),
+    }
+}
+
+fn as_integer(number: &Number) -> Option<i128> {
+    number.as_i64()
+        .map(i128::from)
+        .or_else(|| number.as_u64().map(i128::from))
+}
+
+impl FilterExpression {
+    /// Parses an expression that may call the functions in the given registry next to the
+    /// built-in ones.
+    pub(crate) fn parse_with_functions(s: &str, functions: &FunctionRegistry) -> Result<Self, JsonSearchParseError> {
+        let mut parser = FilterParser {
+            source: s,
+            position: 0,
+            functions,
+        };
+
+        let expression = parser.parse_or()?;
+        parser.skip_whitespace();
+
+        if parser.position < s.len() {
+            return Err(parser.error("unexpected trailing characters"));
+        }
+
+        Ok(expression)
+    }
+}
+
+impl FromStr for FilterExpression {
+    type Err = JsonSearchParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FilterExpression::parse_with_functions(s, &FunctionRegistry::default())
+    }
+}
+
+impl FilterQuery {
+    pub(crate) fn parse_with_functions(s: &str, functions: &FunctionRegistry) -> Result<Self, JsonSearchParseError> {
+        let (root, parts) = parse_segments(split_segments(s), functions)?;
+
+        let relative = match root {
+            "@" => true,
+            "$" => false,
+            _ => return Err(JsonSearchParseError::InvalidFilter(s.to_string(), "a query should start with '@' or '$'".to_string())),
+        };
+
+        Ok(FilterQuery {
+            relative,
+            parts,
+        })
+    }
+}
+
+impl FromStr for FilterQuery {
+    type Err = JsonSearchParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FilterQuery::parse_with_functions(s, &FunctionRegistry::default())
+    }
+}
+
+/// Recursive descent parser for filter expressions. Logical operators bind in the usual order:
+/// `!` binds tightest, followed by `&&` and then `||`.
+struct FilterParser<'a> {
+    source: &'a str,
+    position: usize,
+    functions: &'a FunctionRegistry,
+}
+
+impl<'a> FilterParser<'a> {
+    fn error(&self, reason: &str) -> JsonSearchParseError {
+        JsonSearchParseError::InvalidFilter(self.source.to_string(), reason.to_string())
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+
+        if self.rest().starts_with(token) {
+            self.position += token.len();
+            return true;
+        }
+
+        false
+    }
+
+    fn parse_or(&mut self) -> Result<FilterExpression, JsonSearchParseError> {
+        let mut expression = self.parse_and()?;
+
+        while self.eat("||") {
+            expression = FilterExpression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> Result<FilterExpression, JsonSearchParseError> {
+        let mut expression = self.parse_unary()?;
+
+        while self.eat("&&") {
+            expression = FilterExpression::And(Box::new(expression), Box::new(self.parse_unary()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn parse_unary(&mut self) -> Result<FilterExpression, JsonSearchParseError> {
+        self.skip_whitespace();
+
+        if self.rest().starts_with('!') && !self.rest().starts_with("!=") {
+            self.position += 1;
+            return Ok(FilterExpression::Not(Box::new(self.parse_unary()?)));
+        }
+
+        if self.eat("(") {
+            let expression = self.parse_or()?;
+
+            if !self.eat(")") {
+                return Err(self.error("expected ')'"));
+            }
+
+            return Ok(expression);
+        }
+
+        let left = self.parse_comparable()?;
+
+        let Some(operator) = self.parse_operator() else {
+            return match left {
+                Comparable::Query(query) => Ok(FilterExpression::Exists(query)),
+                Comparable::Function(call) if call.result() != FunctionType::Value => Ok(FilterExpression::Function(call)),
+                Comparable::Function(call) => Err(self.error(&format!("the result of {}() should be compared to something", call.function().name()))),
+                Comparable::Literal(_) | Comparable::Key => Err(self.error("a literal should be compared to something")),
+            };
+        };
+
+        let right = self.parse_comparable()?;
+
+        for operand in [&left, &right] {
+            if let Comparable::Function(call) = operand {
+                if call.result() != FunctionType::Value {
+                    return Err(self.error(&format!("the result of {}() cannot be compared", call.function().name())));
+                }
+            }
+        }
+
+        Ok(FilterExpression::Comparison(left, operator, right))
+    }
+
+    fn parse_operator(&mut self) -> Option<ComparisonOperator> {
+        let operators = [
+            ("==", ComparisonOperator::Equal),
+            ("!=", ComparisonOperator::NotEqual),
+            ("<=", ComparisonOperator::LessOrEqual),
+            (">=", ComparisonOperator::GreaterOrEqual),
+            ("<", ComparisonOperator::Less),
+            (">", ComparisonOperator::Greater),
+        ];
+
+        operators.into_iter()
+            .find(|(token, _)| self.eat(token))
+            .map(|(_, operator)| operator)
+    }
+
+    fn parse_comparable(&mut self) -> Result<Comparable, JsonSearchParseError> {
+        self.skip_whitespace();
+
+        if self.eat("@~") {
+            return Ok(Comparable::Key);
+        }
+
+        match self.rest().chars().next() {
+            Some('@' | '$') => Ok(Comparable::Query(self.parse_query()?)),
+            Some('"' | '\'') => Ok(Comparable::Literal(Value::String(self.parse_string()?))),
+            Some(c) if c == '-' || c.is_ascii_digit() => Ok(Comparable::Literal(self.parse_number()?)),
+            Some(c) if c.is_ascii_lowercase() && self.function_name().is_some() => Ok(Comparable::Function(self.parse_function()?)),
+            Some(_) => {
+                for (token, value) in [("true", Value::Bool(true)), ("false", Value::Bool(false)), ("null", Value::Null)] {
+                    if self.eat(token) {
+                        return Ok(Comparable::Literal(value));
+                    }
+                }
+
+                Err(self.error("expected a query or a literal"))
+            }
+            None => Err(self.error("unexpected end of expression")),
+        }
+    }
+
+    /// Returns the name of the function called at the current position, if there is one.
+    fn function_name(&self) -> Option<&'a str> {
+        let rest = self.rest();
+        let end = rest.find(|c: char| !(c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'))?;
+
+        match rest[end..].starts_with('(') {
+            true => Some(&rest[..end]),
+            false => None,
+        }
+    }
+
+    fn parse_function(&mut self) -> Result<FunctionCall, JsonSearchParseError> {
+        let name = self.function_name()
+            .ok_or_else(|| self.error("expected a function call"))?;
+
+        let function = self.functions.get(name)
+            .ok_or_else(|| self.error(&format!("unknown function '{}'", name)))?;
+
+        self.position += name.len() + 1;
+        let mut arguments = vec![];
+
+        if !self.eat(")") {
+            loop {
+                arguments.push(self.parse_comparable()?);
+
+                if self.eat(")") {
+                    break;
+                }
+
+                if !self.eat(",") {
+                    return Err(self.error("expected ',' or ')'"));
+                }
+            }
+        }
+
+        FunctionCall::new(function, arguments)
+            .map_err(|reason| self.error(&reason))
+    }
+
+    /// Consumes a query up to the first whitespace or operator character that is not nested
+    /// inside brackets or quotes.
+    fn parse_query(&mut self) -> Result<FilterQuery, JsonSearchParseError> {
+        let rest = self.rest();
+        let mut depth = 0;
+        let mut quote = None;
+        let mut escaped = false;
+        let mut end = rest.len();
+        let mut previous = None;
+
+        for (i, c) in rest.char_indices() {
+            let is_exclusion = c == '!' && matches!(previous, Some('.' | '*'));
+            previous = Some(c);
+
+            if let Some(q) = quote {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    _ if c == q => quote = None,
+                    _ => {}
+                }
+
+                continue;
+            }
+
+            match c {
+                '"' | '\'' if depth > 0 => quote = Some(c),
+                '[' | '(' | '{' => depth += 1,
+                ']' | ')' | '}' if depth > 0 => depth -= 1,
+                _ if depth == 0 && !is_exclusion && (c.is_whitespace() || "=!<>&|),".contains(c)) => {
+                    end = i;
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        self.position += end;
+
+        FilterQuery::parse_with_functions(&rest[..end], self.functions)
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonSearchParseError> {
+        let (value, length) = parse_string_literal(self.rest())
+            .ok_or_else(|| self.error("invalid string literal"))?;
+
+        self.position += length;
+        Ok(value)
+    }
+
+    fn parse_number(&mut self) -> Result<Value, JsonSearchParseError> {
+        let rest = self.rest();
+        let end = rest.find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+            .unwrap_or(rest.len());
+
+        let value = serde_json::from_str::<Value>(&rest[..end])
+            .ok()
+            .filter(Value::is_number)
+            .ok_or_else(|| self.error("invalid number literal"))?;
+
+        // Serde JSON only parses floats approximately by default, so the value is taken from the
+        // standard library parser to make sure literals are written back out unchanged.
+        let value = match value.is_f64() {
+            true => rest[..end].parse::<f64>()
+                .map(Value::from)
+                .unwrap_or(value),
+            false => value,
+        };
+
+        self.position += end;
+        Ok(value)
+    }
+}
+
+impl FilterExpression {
+    /// Binding strength of the expression, used to decide where parentheses are needed when the
+    /// expression is written back out.
+    fn precedence(&self) -> u8 {
+        match self {
+            FilterExpression::Or(_, _) => 0,
+            FilterExpression::And(_, _) => 1,
+            _ => 2,
+        }
+    }
+
+    fn fmt_binary(&self, f: &mut Formatter<'_>, left: &FilterExpression, operator: &str, right: &FilterExpression) -> std::fmt::Result {
+        // Operators associate to the left, so a right operand of the same strength needs
+        // parentheses to be parsed back into the same tree.
+        left.fmt_operand(f, left.precedence() < self.precedence())?;
+        write!(f, " {} ", operator)?;
+        right.fmt_operand(f, right.precedence() <= self.precedence())
+    }
+
+    fn fmt_operand(&self, f: &mut Formatter<'_>, parenthesize: bool) -> std::fmt::Result {
+        if parenthesize {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl Display for FilterExpression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterExpression::Or(left, right) => self.fmt_binary(f, left, "||", right),
+            FilterExpression::And(left, right) => self.fmt_binary(f, left, "&&", right),
+            FilterExpression::Not(inner) => match inner.as_ref() {
+                FilterExpression::Exists(_) | FilterExpression::Not(_) | FilterExpression::Function(_) => write!(f, "!{}", inner),
+                _ => write!(f, "!({})", inner),
+            },
+            FilterExpression::Comparison(left, operator, right) => write!(f, "{} {} {}", left, operator, right),
+            FilterExpression::Exists(query) => write!(f, "{}", query),
+            FilterExpression::Function(call) => write!(f, "{}", call),
+        }
+    }
+}
+
+impl Display for ComparisonOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let operator = match self {
+            ComparisonOperator::Equal => "==",
+            ComparisonOperator::NotEqual => "!=",
+            ComparisonOperator::Less => "<",
+            ComparisonOperator::LessOrEqual => "<=",
+            ComparisonOperator::Greater => ">",
+            ComparisonOperator::GreaterOrEqual => ">=",
+        };
+
+        write!(f, "{}", operator)
+    }
+}
+
+impl Display for Comparable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Comparable::Literal(value) => write!(f, "{}", value),
+            Comparable::Query(query) => write!(f, "{}", query),
+            Comparable::Function(call) => write!(f, "{}", call),
+            Comparable::Key => write!(f, "@~"),
+        }
+    }
+}
+
+impl Display for FilterQuery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", if self.relative { "@" } else { "$" })?;
+
+        for part in &self.parts {
+            part.fmt_segment(f)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,389 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use regex::Regex;
+use serde_json::Value;
+use crate::json_search::filter::{Comparable, FilterContext};
+use crate::json_search::function_registry::FunctionRegistry;
+use crate::json_search::JsonSearchResolveError;
+
+/// Types of the arguments and results of filter functions, following the type system of
+/// RFC 9535.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FunctionType {
+    /// A single value, or nothing when a query does not select exactly one value. Written as a
+    /// literal, a singular query like `@.name` or a function returning a value.
+    Value,
+
+    /// True or false. Functions returning a logical can be used as a test in a filter, but cannot
+    /// be compared.
+    Logical,
+
+    /// Every value selected by a query like `@.*`. Used as a test, a list of nodes is true when
+    /// it is not empty.
+    Nodes,
+}
+
+/// Function extensions that can be called inside filter expressions, like
+/// `$.users[?length(@.roles) > 2]`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterFunction {
+    /// `length(value)` returns the number of characters in a string, elements in an array or
+    /// members in an object, and nothing for any other value.
+    Length,
+
+    /// `count(nodes)` returns the number of values selected by a query.
+    Count,
+
+    /// `match(value, pattern)` checks if the whole string matches the regular expression.
+    Match,
+
+    /// `search(value, pattern)` checks if any part of the string matches the regular expression.
+    Search,
+
+    /// `value(nodes)` returns the value selected by a query, or nothing when it does not select
+    /// exactly one value.
+    Value,
+
+    /// A function from a [FunctionRegistry], which is looked up by name when the search is
+    /// resolved.
+    Custom {
+        name: String,
+        parameters: Vec<FunctionType>,
+        result: FunctionType,
+    },
+}
+
+/// A call to a filter function. The arguments are checked against the signature of the function
+/// when the call is created, so a call that exists can always be evaluated.
+#[derive(Debug, Clone)]
+pub struct FunctionCall {
+    function: FilterFunction,
+    arguments: Vec<Comparable>,
+    pattern: Pattern,
+}
+
+/// The regular expression of a call to `match()` or `search()`.
+#[derive(Debug, Clone)]
+enum Pattern {
+    /// A literal pattern, compiled once when the call is created. Invalid patterns never match.
+    Literal(Option<Regex>),
+
+    /// A pattern that comes from a query or another function, compiled whenever the call is
+    /// evaluated. Calls to other functions have no pattern either.
+    Dynamic,
+}
+
+/// The arguments and result of a function call, borrowing from the document where possible.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FunctionValue<'a> {
+    Value(Option<Cow<'a, Value>>),
+    Logical(bool),
+    Nodes(Vec<&'a Value>),
+}
+
+impl FilterFunction {
+    pub fn name(&self) -> &str {
+        match self {
+            FilterFunction::Length => "length",
+            FilterFunction::Count => "count",
+            FilterFunction::Match => "match",
+            FilterFunction::Search => "search",
+            FilterFunction::Value => "value",
+            FilterFunction::Custom { name, .. } => name,
+        }
+    }
+
+    pub fn parameters(&self) -> &[FunctionType] {
+        match self {
+            FilterFunction::Length => &[FunctionType::Value],
+            FilterFunction::Count | FilterFunction::Value => &[FunctionType::Nodes],
+            FilterFunction::Match | FilterFunction::Search => &[FunctionType::Value, FunctionType::Value],
+            FilterFunction::Custom { parameters, .. } => parameters,
+        }
+    }
+
+    pub fn result(&self) -> FunctionType {
+        match self {
+            FilterFunction::Length | FilterFunction::Count | FilterFunction::Value => FunctionType::Value,
+            FilterFunction::Match | FilterFunction::Search => FunctionType::Logical,
+            FilterFunction::Custom { result, .. } => *result,
+        }
+    }
+
+    fn evaluate<'a>(&self, mut arguments: Vec<FunctionValue<'a>>, pattern: &Pattern, functions: &FunctionRegistry) -> Result<FunctionValue<'a>, JsonSearchResolveError> {
+        let result = match (self, arguments.as_mut_slice()) {
+            (FilterFunction::Length, [FunctionValue::Value(value)]) => {
+                let length = match value.as_deref() {
+                    Some(Value::String(string)) => Some(string.chars().count()),
+                    Some(Value::Array(array)) => Some(array.len()),
+                    Some(Value::Object(map)) => Some(map.len()),
+                    _ => None,
+                };
+
+                FunctionValue::Value(length.map(|length| Cow::Owned(Value::from(length))))
+            }
+            (FilterFunction::Count, [FunctionValue::Nodes(nodes)]) => {
+                FunctionValue::Value(Some(Cow::Owned(Value::from(nodes.len()))))
+            }
+            (FilterFunction::Value, [FunctionValue::Nodes(nodes)]) => {
+                FunctionValue::Value(match nodes.len() {
+                    1 => nodes.pop().map(Cow::Borrowed),
+                    _ => None,
+                })
+            }
+            (FilterFunction::Match | FilterFunction::Search, [FunctionValue::Value(value), FunctionValue::Value(source)]) => {
+                let matches = match (value.as_deref(), source.as_deref(), pattern) {
+                    (Some(Value::String(value)), Some(Value::String(_)), Pattern::Literal(regex)) => {
+                        regex.as_ref().is_some_and(|regex| regex.is_match(value))
+                    }
+                    (Some(Value::String(value)), Some(Value::String(source)), Pattern::Dynamic) => {
+                        to_regex(source, matches!(self, FilterFunction::Match))
+                            .is_some_and(|regex| regex.is_match(value))
+                    }
+                    _ => false,
+                };
+
+                FunctionValue::Logical(matches)
+            }
+            (FilterFunction::Custom { name, parameters, result }, _) => {
+                return functions.call(name, parameters, *result, arguments);
+            }
+            _ => unreachable!("arguments are checked against the signature when the call is created"),
+        };
+
+        Ok(result)
+    }
+}
+
+impl FunctionValue<'_> {
+    pub fn value_type(&self) -> FunctionType {
+        match self {
+            FunctionValue::Value(_) => FunctionType::Value,
+            FunctionValue::Logical(_) => FunctionType::Logical,
+            FunctionValue::Nodes(_) => FunctionType::Nodes,
+        }
+    }
+}
+
+impl FunctionType {
+    /// Checks if the argument can be passed for a parameter of this type. Queries can always be
+    /// passed as nodes or as a logical, but only singular queries can be passed as a value.
+    fn accepts(&self, argument: &Comparable) -> bool {
+        match (self, argument) {
+            (FunctionType::Value, Comparable::Literal(_) | Comparable::Key) => true,
+            (FunctionType::Value, Comparable::Query(query)) => query.is_singular(),
+            (FunctionType::Nodes | FunctionType::Logical, Comparable::Query(_)) => true,
+            (FunctionType::Logical, Comparable::Function(call)) => call.result() != FunctionType::Value,
+            (parameter, Comparable::Function(call)) => call.result() == *parameter,
+            _ => false,
+        }
+    }
+}
+
+impl FunctionCall {
+    /// Creates a call after checking the number and types of the arguments, returning the reason
+    /// when they do not match the signature of the function.
+    pub fn new(function: FilterFunction, arguments: Vec<Comparable>) -> Result<Self, String> {
+        let parameters = function.parameters();
+
+        if arguments.len() != parameters.len() {
+            return Err(format!("{}() expects {} argument(s), but got {}", function.name(), parameters.len(), arguments.len()));
+        }
+
+        for (i, (parameter, argument)) in parameters.iter().zip(&arguments).enumerate() {
+            if !parameter.accepts(argument) {
+                return Err(format!("argument {} of {}() should be of type {:?}", i + 1, function.name(), parameter));
+            }
+        }
+
+        let pattern = match (&function, arguments.as_slice()) {
+            (FilterFunction::Match | FilterFunction::Search, [_, Comparable::Literal(Value::String(source))]) => {
+                Pattern::Literal(to_regex(source, function == FilterFunction::Match))
+            }
+            _ => Pattern::Dynamic,
+        };
+
+        Ok(FunctionCall {
+            function,
+            arguments,
+            pattern,
+        })
+    }
+
+    pub fn function(&self) -> &FilterFunction {
+        &self.function
+    }
+
+    pub fn arguments(&self) -> &[Comparable] {
+        &self.arguments
+    }
+
+    pub fn result(&self) -> FunctionType {
+        self.function.result()
+    }
+
+    /// Checks if the call and its arguments only use built-in functions and queries that start at
+    /// the current value, so the call cannot fail.
+    pub(crate) fn is_self_contained(&self) -> bool {
+        !matches!(self.function, FilterFunction::Custom { .. }) && self.arguments.iter().all(Comparable::is_self_contained)
+    }
+
+    /// Evaluates the call, returning an error when a user-defined function cannot be called or
+    /// returns a result of a different type than it declared.
+    pub(crate) fn evaluate<'a>(&'a self, current: &'a Value, context: &FilterContext<'a>) -> Result<FunctionValue<'a>, JsonSearchResolveError> {
+        let arguments = self.function.parameters()
+            .iter()
+            .zip(&self.arguments)
+            .map(|(parameter, argument)| match (parameter, argument) {
+                (FunctionType::Value, argument) => argument.evaluate(current, context).map(FunctionValue::Value),
+                (FunctionType::Logical, Comparable::Query(query)) => query.select(current, context).map(|nodes| FunctionValue::Logical(!nodes.is_empty())),
+                (FunctionType::Logical, Comparable::Function(call)) => call.test(current, context).map(FunctionValue::Logical),
+                (FunctionType::Nodes, Comparable::Query(query)) => query.select(current, context).map(FunctionValue::Nodes),
+                (FunctionType::Nodes, Comparable::Function(call)) => call.evaluate(current, context),
+                (_, Comparable::Literal(_) | Comparable::Key) => unreachable!("literals and keys are only accepted as values"),
+            })
+            .collect::<Result<_, _>>()?;
+
+        self.function.evaluate(arguments, &self.pattern, context.functions)
+    }
+
+    /// Evaluates a call that is used as a test in a filter expression.
+    pub(crate) fn test(&self, current: &Value, context: &FilterContext) -> Result<bool, JsonSearchResolveError> {
+        let result = match self.evaluate(current, context)? {
+            FunctionValue::Logical(result) => result,
+            FunctionValue::Nodes(nodes) => !nodes.is_empty(),
+            FunctionValue::Value(_) => false,
+        };
+
+        Ok(result)
+    }
+}
+
+/// Converts an I-Regexp (RFC 9485) pattern into a regular expression. The only difference that
+/// matters is that `.` does not match carriage returns either.
+fn to_regex(pattern: &str, anchored: bool) -> Option<Regex> {
+    let mut converted = String::new();
+    let mut escaped = false;
+    let mut in_class = false;
+
+    for c in pattern.chars() {
+        match c {
+            _ if escaped => {
+                escaped = false;
+                converted.push(c);
+            }
+            '\\' => {
+                escaped = true;
+                converted.push(c);
+            }
+            '.' if !in_class => converted.push_str(r"[^\n\r]"),
+            '[' => {
+                in_class = true;
+                converted.push(c);
+            }
+            ']' => {
+                in_class = false;
+                converted.push(c);
+            }
+            _ => converted.push(c),
+        }
+    }
+
+    match anchored {
+        true => Regex::new(&format!("^(?:{})$", converted)).ok(),
+        false => Regex::new(&converted).ok(),
+    }
+}
+
+impl FromStr for FilterFunction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "length" => Ok(FilterFunction::Length),
+            "count" => Ok(FilterFunction::Count),
+            "match" => Ok(FilterFunction::Match),
+            "search" => Ok(FilterFunction::Search),
+            "value" => Ok(FilterFunction::Value),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Calls are equal when they call the same function with the same arguments, as the compiled
+/// pattern follows from those.
+impl PartialEq for FunctionCall {
+    fn eq(&self, other: &Self) -> bool {
+        self.function == other.function && self.arguments == other.arguments
+    }
+}
+
+impl Display for FunctionCall {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}(", self.function.name())?;
+
+        for (i, argument) in self.arguments.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}", argument)?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::json_search::filter::{Comparable, FilterContext, FilterQuery};
+    use crate::json_search::filter_function::{to_regex, FilterFunction, FunctionCall, FunctionType, Pattern};
+    use crate::json_search::function_registry::FunctionRegistry;
+
+    #[test]
+    fn i_regexp_patterns_are_converted_correctly() {
+        assert!(to_regex("a.c", true).unwrap().is_match("abc"));
+        assert!(!to_regex("a.c", true).unwrap().is_match("a\rc"));
+        assert!(!to_regex("a.c", true).unwrap().is_match("abcd"));
+        assert!(to_regex("a.c", false).unwrap().is_match("xabcd"));
+        assert!(to_regex(r"a\.c", true).unwrap().is_match("a.c"));
+        assert!(!to_regex(r"a\.c", true).unwrap().is_match("abc"));
+        assert!(to_regex("[.]", true).unwrap().is_match("."));
+        assert!(to_regex("(", true).is_none());
+    }
+
+    #[test]
+    fn arguments_are_checked_against_the_signature() {
+        let singular = Comparable::Query(FilterQuery { relative: true, parts: vec![] });
+        let length = FunctionCall::new(FilterFunction::Length, vec![singular.clone()]).unwrap();
+
+        assert_eq!(length.result(), FunctionType::Value);
+        assert!(FunctionCall::new(FilterFunction::Length, vec![]).is_err());
+        assert!(FunctionCall::new(FilterFunction::Count, vec![Comparable::Literal(1.into())]).is_err());
+        assert!(FunctionCall::new(FilterFunction::Match, vec![singular.clone(), Comparable::Function(length.clone())]).is_ok());
+        assert!(FunctionCall::new(FilterFunction::Count, vec![Comparable::Function(length)]).is_err());
+    }
+
+    #[test]
+    fn literal_patterns_are_compiled_once() {
+        let current = Comparable::Query(FilterQuery { relative: true, parts: vec![] });
+        let literal = FunctionCall::new(FilterFunction::Match, vec![current.clone(), Comparable::Literal(json!("a.c"))]).unwrap();
+        let invalid = FunctionCall::new(FilterFunction::Search, vec![current.clone(), Comparable::Literal(json!("("))]).unwrap();
+        let dynamic = FunctionCall::new(FilterFunction::Match, vec![current.clone(), current]).unwrap();
+
+        assert!(matches!(literal.pattern, Pattern::Literal(Some(_))));
+        assert!(matches!(invalid.pattern, Pattern::Literal(None)));
+        assert!(matches!(dynamic.pattern, Pattern::Dynamic));
+
+        let root = json!(null);
+        let context = FilterContext { root: &root, key: None, functions: FunctionRegistry::empty() };
+
+        assert_eq!(literal.test(&json!("abc"), &context), Ok(true));
+        assert_eq!(literal.test(&json!("abcd"), &context), Ok(false));
+        assert_eq!(invalid.test(&json!("("), &context), Ok(false));
+        assert_eq!(dynamic.test(&json!("a.c"), &context), Ok(true));
+        assert_eq!(dynamic.test(&json!("("), &context), Ok(false));
+    }
+}
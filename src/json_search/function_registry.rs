@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::fmt::{Debug, Formatter};
+use thiserror::Error;
+use crate::json_search::filter_function::{FilterFunction, FunctionType, FunctionValue};
+use crate::json_search::JsonSearchResolveError;
+
+type Implementation = Box<dyn for<'a> Fn(Vec<FunctionValue<'a>>) -> FunctionValue<'a> + Send + Sync>;
+
+/// User-defined functions that can be called in filter expressions next to the built-in ones,
+/// like `$.sessions[?is_uuid(@.id)]`. The same registry should be used to parse a search and to
+/// resolve it, as the signatures are checked while parsing and the implementations are looked up
+/// while resolving.
+#[derive(Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, RegisteredFunction>,
+}
+
+struct RegisteredFunction {
+    parameters: Vec<FunctionType>,
+    result: FunctionType,
+    implementation: Implementation,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum FunctionRegistryError {
+    #[error("Invalid function name '{0}', expected a lowercase letter followed by lowercase letters, digits or underscores")]
+    InvalidName(String),
+
+    #[error("Function name '{0}' is already used by a built-in function")]
+    BuiltIn(String),
+}
+
+impl FunctionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry without any functions, shared by everything that resolves searches without one.
+    pub(crate) fn empty() -> &'static FunctionRegistry {
+        static EMPTY: OnceLock<FunctionRegistry> = OnceLock::new();
+
+        EMPTY.get_or_init(FunctionRegistry::default)
+    }
+
+    /// Registers a function with the given signature. The implementation receives its arguments
+    /// in the order of the parameters, each converted to the declared type, and should return a
+    /// result of the declared type. Registering a name again replaces the previous function.
+    pub fn register<F>(&mut self, name: &str, parameters: &[FunctionType], result: FunctionType, implementation: F) -> Result<(), FunctionRegistryError>
+        where
+            F: for<'a> Fn(Vec<FunctionValue<'a>>) -> FunctionValue<'a> + Send + Sync + 'static,
+    {
+        let mut chars = name.chars();
+
+        let is_valid = chars.next().is_some_and(|c| c.is_ascii_lowercase())
+            && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+        if !is_valid {
+            return Err(FunctionRegistryError::InvalidName(name.to_string()));
+        }
+
+        if name.parse::<FilterFunction>().is_ok() {
+            return Err(FunctionRegistryError::BuiltIn(name.to_string()));
+        }
+
+        self.functions.insert(name.to_string(), RegisteredFunction {
+            parameters: parameters.to_vec(),
+            result,
+            implementation: Box::new(implementation),
+        });
+
+        Ok(())
+    }
+
+    /// Looks up a function by name, returning built-in functions before registered ones.
+    pub fn get(&self, name: &str) -> Option<FilterFunction> {
+        if let Ok(function) = name.parse() {
+            return Some(function);
+        }
+
+        self.functions.get(name)
+            .map(|function| FilterFunction::Custom {
+                name: name.to_string(),
+                parameters: function.parameters.clone(),
+                result: function.result,
+            })
+    }
+
+    /// Calls a registered function, returning an error when no function with the same signature
+    /// is registered or when it returns a result of a different type than it declared.
+    pub(crate) fn call<'a>(&self, name: &str, parameters: &[FunctionType], result: FunctionType, arguments: Vec<FunctionValue<'a>>) -> Result<FunctionValue<'a>, JsonSearchResolveError> {
+        let function = self.functions.get(name)
+            .filter(|function| function.parameters == parameters && function.result == result)
+            .ok_or_else(|| JsonSearchResolveError::UnknownFunction(name.to_string()))?;
+
+        let value = (function.implementation)(arguments);
+
+        if value.value_type() != result {
+            return Err(JsonSearchResolveError::InvalidFunctionResult(name.to_string(), result, value.value_type()));
+        }
+
+        Ok(value)
+    }
+}
+
+impl Debug for FunctionRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set()
+            .entries(self.functions.keys())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::str::FromStr;
+    use serde_json::{json, Value};
+    use crate::json_path::JsonPath;
+    use crate::json_search::{JsonSearch, JsonSearchParseError, JsonSearchResolveError};
+    use crate::json_search::filter_function::{FunctionType, FunctionValue};
+    use crate::json_search::function_registry::{FunctionRegistry, FunctionRegistryError};
+    use crate::json_search::search_syntax::SearchSyntax;
+
+    fn registry() -> FunctionRegistry {
+        let mut functions = FunctionRegistry::new();
+
+        functions.register("is_uuid", &[FunctionType::Value], FunctionType::Logical, |arguments| {
+            let is_uuid = match arguments.first() {
+                Some(FunctionValue::Value(Some(value))) => value.as_str().is_some_and(|id| {
+                    id.len() == 36 && id.char_indices().all(|(i, c)| match i {
+                        8 | 13 | 18 | 23 => c == '-',
+                        _ => c.is_ascii_hexdigit(),
+                    })
+                }),
+                _ => false,
+            };
+
+            FunctionValue::Logical(is_uuid)
+        }).unwrap();
+
+        functions.register("before", &[FunctionType::Value, FunctionType::Value], FunctionType::Logical, |arguments| {
+            match arguments.as_slice() {
+                [FunctionValue::Value(Some(left)), FunctionValue::Value(Some(right))] => {
+                    FunctionValue::Logical(matches!((left.as_str(), right.as_str()), (Some(left), Some(right)) if left < right))
+                }
+                _ => FunctionValue::Logical(false),
+            }
+        }).unwrap();
+
+        functions.register("first", &[FunctionType::Nodes], FunctionType::Value, |mut arguments| {
+            match arguments.pop() {
+                Some(FunctionValue::Nodes(nodes)) => FunctionValue::Value(nodes.first().map(|value| Cow::Borrowed(*value))),
+                _ => FunctionValue::Value(None),
+            }
+        }).unwrap();
+
+        functions
+    }
+
+    #[test]
+    fn function_names_are_validated() {
+        fn noop(_: Vec<FunctionValue>) -> FunctionValue {
+            FunctionValue::Logical(true)
+        }
+
+        let mut functions = FunctionRegistry::new();
+
+        assert_eq!(functions.register("isUuid", &[], FunctionType::Logical, noop), Err(FunctionRegistryError::InvalidName("isUuid".to_string())));
+        assert_eq!(functions.register("_a", &[], FunctionType::Logical, noop), Err(FunctionRegistryError::InvalidName("_a".to_string())));
+        assert_eq!(functions.register("length", &[], FunctionType::Logical, noop), Err(FunctionRegistryError::BuiltIn("length".to_string())));
+        assert_eq!(functions.register("always_2", &[], FunctionType::Logical, noop), Ok(()));
+    }
+
+    #[test]
+    fn calls_are_checked_against_the_registered_signature() {
+        let functions = registry();
+        let parse = |s: &str, syntax| JsonSearch::parse_with_functions(s, syntax, &functions);
+
+        assert!(parse("$.sessions[?is_uuid(@.id)]", SearchSyntax::Native).is_ok());
+        assert!(parse("$.sessions[?is_uuid(@.id)]", SearchSyntax::Rfc9535).is_ok());
+        assert!(parse(r#"$.tokens[?before(@.expires, "2026-01-01") && first(@.*) == 1]"#, SearchSyntax::Native).is_ok());
+
+        assert!(matches!(JsonSearch::from_str("$.sessions[?is_uuid(@.id)]"), Err(JsonSearchParseError::InvalidFilter(_, _))));
+        assert!(matches!(parse("$[?is_guid(@.id)]", SearchSyntax::Native), Err(JsonSearchParseError::InvalidFilter(_, _))));
+        assert!(matches!(parse("$[?is_uuid(@.id, @.a)]", SearchSyntax::Native), Err(JsonSearchParseError::InvalidFilter(_, _))));
+        assert!(matches!(parse("$[?is_uuid(@.*)]", SearchSyntax::Native), Err(JsonSearchParseError::InvalidFilter(_, _))));
+        assert!(matches!(parse("$[?is_uuid(@.id) == true]", SearchSyntax::Native), Err(JsonSearchParseError::InvalidFilter(_, _))));
+        assert!(matches!(parse("$[?before(@.a)]", SearchSyntax::Rfc9535), Err(JsonSearchParseError::InvalidJsonPath(_, 3, _))));
+        assert!(matches!(parse("$[?first(@.*)]", SearchSyntax::Rfc9535), Err(JsonSearchParseError::InvalidJsonPath(_, _, _))));
+    }
+
+    #[test]
+    fn registered_functions_are_called_when_resolving() {
+        let functions = registry();
+
+        let target_value = json!({
+            "sessions": [
+                { "id": "0f8fad5b-d9cb-469f-a165-70867728950e", "expires": "2025-06-01" },
+                { "id": "not-a-uuid", "expires": "2025-01-01" },
+                { "id": "7c9e6679-7425-40de-944b-e07fc1f90ae7", "expires": "2027-01-01" },
+            ],
+        });
+
+        let resolve = |s: &str| JsonSearch::parse_with_functions(s, SearchSyntax::Native, &functions)
+            .unwrap()
+            .resolve_with_functions(&target_value, &functions);
+
+        assert_eq!(resolve("$.sessions[?is_uuid(@.id)]"), Ok(vec![
+            JsonPath::from(["sessions", "0"]),
+            JsonPath::from(["sessions", "2"]),
+        ]));
+
+        assert_eq!(resolve(r#"$.sessions[?before(@.expires, "2026-01-01")].id"#), Ok(vec![
+            JsonPath::from(["sessions", "0", "id"]),
+            JsonPath::from(["sessions", "1", "id"]),
+        ]));
+
+        assert_eq!(resolve(r#"$[?first(@.*.expires) == "2025-06-01"]"#), Ok(vec![
+            JsonPath::from(["sessions"]),
+        ]));
+
+        let search = JsonSearch::parse_with_functions("$.sessions[?is_uuid(@.id)]", SearchSyntax::Native, &functions).unwrap();
+        assert_eq!(search.resolve(&target_value), Err(JsonSearchResolveError::UnknownFunction("is_uuid".to_string())));
+        assert_eq!(search.resolve_with_functions(&target_value, &FunctionRegistry::new()), Err(JsonSearchResolveError::UnknownFunction("is_uuid".to_string())));
+        assert_eq!(search.to_string(), "$.sessions[?is_uuid(@.id)]");
+    }
+
+    #[test]
+    fn results_of_the_wrong_type_return_an_err() {
+        let mut functions = FunctionRegistry::new();
+
+        functions.register("broken", &[], FunctionType::Logical, |_| FunctionValue::Value(Some(Cow::Owned(Value::Bool(true))))).unwrap();
+
+        let search = JsonSearch::parse_with_functions("$[?broken()]", SearchSyntax::Native, &functions).unwrap();
+        assert_eq!(search.resolve_with_functions(&json!([1, 2]), &functions), Err(JsonSearchResolveError::InvalidFunctionResult("broken".to_string(), FunctionType::Logical, FunctionType::Value)));
+    }
+
+    #[test]
+    fn failed_calls_are_not_skipped_by_optional_parts() {
+        let mut functions = FunctionRegistry::new();
+
+        functions.register("broken", &[], FunctionType::Logical, |_| FunctionValue::Value(None)).unwrap();
+
+        let target_value = json!({ "a": [1], "b": { "c": [2] } });
+        let error = || JsonSearchResolveError::InvalidFunctionResult("broken".to_string(), FunctionType::Logical, FunctionType::Value);
+
+        for s in ["$.*[?broken()]", "$.**[?broken()]", "$.a?[?broken()]", "$[a,b][?broken()]"] {
+            let search = JsonSearch::parse_with_functions(s, SearchSyntax::Native, &functions).unwrap();
+
+            assert_eq!(search.resolve_with_functions(&target_value, &functions), Err(error()), "{}", s);
+            assert_eq!(search.resolve_iter_with_functions(&target_value, &functions).last(), Some(Err(error())), "{}", s);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn searches_with_registered_functions_do_not_deserialize() {
+        let functions = registry();
+
+        let search = JsonSearch::parse_with_functions("$.sessions[?is_uuid(@.id)]", SearchSyntax::Native, &functions).unwrap();
+        let serialized = serde_json::to_string(&search).unwrap();
+
+        assert_eq!(serialized, r#""$.sessions[?is_uuid(@.id)]""#);
+        assert!(serde_json::from_str::<JsonSearch>(&serialized).is_err());
+        assert_eq!(JsonSearch::parse_with_functions(&serde_json::from_str::<String>(&serialized).unwrap(), SearchSyntax::Native, &functions), Ok(search));
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use regex::Regex;
+use crate::json_search::JsonSearchParseError;
+
+/// Pattern that object keys are matched against, either a glob like `cpu_*` where `*` matches any
+/// sequence of characters, or a regular expression between slashes like `/^disk_\d+$/`.
+#[derive(Debug, Clone)]
+pub enum KeyPattern {
+    Glob(String),
+    Regex(Regex),
+}
+
+impl KeyPattern {
+    pub fn matches(&self, key: &str) -> bool {
+        match self {
+            KeyPattern::Glob(glob) => glob_matches(glob, key),
+            KeyPattern::Regex(regex) => regex.is_match(key),
+        }
+    }
+}
+
+fn glob_matches(glob: &str, key: &str) -> bool {
+    let mut pieces = glob.split('*');
+
+    let Some(mut rest) = pieces.next().and_then(|first| key.strip_prefix(first)) else {
+        return false;
+    };
+
+    let mut pieces: Vec<&str> = pieces.collect();
+
+    let Some(last) = pieces.pop() else {
+        return rest.is_empty();
+    };
+
+    for piece in pieces {
+        match rest.find(piece) {
+            Some(position) => rest = &rest[position + piece.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+impl PartialEq for KeyPattern {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (KeyPattern::Glob(left), KeyPattern::Glob(right)) => left == right,
+            (KeyPattern::Regex(left), KeyPattern::Regex(right)) => left.as_str() == right.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for KeyPattern {
+    type Err = JsonSearchParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some(pattern) = s.strip_prefix('/').and_then(|s| s.strip_suffix('/')).filter(|_| s.len() > 1) else {
+            return Ok(KeyPattern::Glob(s.to_string()));
+        };
+
+        Regex::new(pattern)
+            .map(KeyPattern::Regex)
+            .map_err(|err| JsonSearchParseError::InvalidRegex(pattern.to_string(), err.to_string()))
+    }
+}
+
+impl Display for KeyPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyPattern::Glob(glob) => write!(f, "{}", glob),
+            KeyPattern::Regex(regex) => {
+                write!(f, "/")?;
+
+                let mut escaped = false;
+
+                for c in regex.as_str().chars() {
+                    if c == '/' && !escaped {
+                        write!(f, "\\")?;
+                    }
+
+                    escaped = c == '\\' && !escaped;
+                    write!(f, "{}", c)?;
+                }
+
+                write!(f, "/")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use crate::json_search::key_pattern::KeyPattern;
+    use crate::json_search::JsonSearchParseError;
+
+    #[test]
+    fn globs_match_correctly() {
+        let glob = KeyPattern::from_str("cpu_*").unwrap();
+        assert!(glob.matches("cpu_0"));
+        assert!(glob.matches("cpu_"));
+        assert!(!glob.matches("gpu_0"));
+
+        let glob = KeyPattern::from_str("*_*_total").unwrap();
+        assert!(glob.matches("a_b_total"));
+        assert!(glob.matches("__total"));
+        assert!(!glob.matches("a_total"));
+        assert!(!glob.matches("a_b_total_x"));
+    }
+
+    #[test]
+    fn regexes_match_correctly() {
+        let regex = KeyPattern::from_str(r"/^disk_\d+$/").unwrap();
+        assert!(regex.matches("disk_12"));
+        assert!(!regex.matches("disk_a"));
+        assert!(!regex.matches("a_disk_1"));
+
+        assert!(matches!(KeyPattern::from_str("/(/"), Err(JsonSearchParseError::InvalidRegex(_, _))));
+    }
+
+    #[test]
+    fn patterns_are_displayed_correctly() {
+        assert_eq!(KeyPattern::from_str("cpu_*").unwrap().to_string(), "cpu_*");
+        assert_eq!(KeyPattern::from_str(r"/^a\/b$/").unwrap().to_string(), r"/^a\/b$/");
+        assert_eq!(KeyPattern::Regex(regex::Regex::new("a/b").unwrap()).to_string(), r"/a\/b/");
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::json_path::{JsonPath, JsonPathParseError};
+use crate::json_path::path_part::PathPart;
+use crate::json_search::search_part::is_capture_name;
+use crate::syntax::split_segments;
+
+/// A path with placeholders like `$.names.{id}`, which are filled in with the keys and indices
+/// bound by the captures of a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplatePart {
+    Literal(PathPart),
+    Placeholder(String),
+}
+
+impl PathTemplate {
+    /// Returns the names of the placeholders in the template, in the order they appear.
+    pub fn placeholders(&self) -> Vec<&str> {
+        self.parts.iter()
+            .filter_map(|part| match part {
+                TemplatePart::Placeholder(name) => Some(name.as_str()),
+                TemplatePart::Literal(_) => None,
+            })
+            .collect()
+    }
+
+    /// Fills in the placeholders, returning `None` when one of them is not bound.
+    pub fn render(&self, captures: &BTreeMap<String, PathPart>) -> Option<JsonPath> {
+        let mut path = JsonPath::default();
+
+        for part in &self.parts {
+            match part {
+                TemplatePart::Literal(part) => path.push(part.clone()),
+                TemplatePart::Placeholder(name) => path.push(captures.get(name)?.clone()),
+            }
+        }
+
+        Some(path)
+    }
+}
+
+impl FromStr for PathTemplate {
+    type Err = JsonPathParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = split_segments(s).into_iter();
+
+        match segments.next() {
+            Some("$") => Ok(()),
+            Some(value) => Err(JsonPathParseError::IncorrectRoot(value.to_string())),
+            None => Err(JsonPathParseError::MissingRoot),
+        }?;
+
+        let parts = segments
+            .map(|segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(name) if is_capture_name(name) => Ok(TemplatePart::Placeholder(name.to_string())),
+                _ => segment.parse().map(TemplatePart::Literal),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(PathTemplate {
+            parts,
+        })
+    }
+}
+
+impl Display for PathTemplate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "$")?;
+
+        for part in &self.parts {
+            match part {
+                TemplatePart::Literal(part) => write!(f, "{}", part.to_segment_string())?,
+                TemplatePart::Placeholder(name) => write!(f, ".{{{}}}", name)?,
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::VecDeque;
+use crate::json_path::JsonPath;
+use crate::json_search::expansion::Expansion;
+use crate::json_search::{JsonSearch, JsonSearchResolveError, ResolveContext, Step};
+
+/// Iterator over the matches of a search, returned by [JsonSearch::resolve_iter]. Only the values
+/// on the way to the current match are kept, together with a position within the children of
+/// each of them, so stopping early skips the rest of the document.
+pub struct ResolveIter<'a> {
+    search: &'a JsonSearch,
+    context: ResolveContext<'a, 'a>,
+
+    /// The steps that still have to be resolved at every level, with the deepest level on top.
+    stack: Vec<Frame<'a>>,
+
+    /// Matches that would be left out by [JsonSearch::resolve] if a branch they belong to still
+    /// fails, each with the level of the stack it was found at. They are yielded in order once
+    /// none of those branches can fail anymore.
+    pending: VecDeque<(JsonPath, usize)>,
+}
+
+/// The steps that continue from a single step, together with whether that step is a branch.
+struct Frame<'a> {
+    expansion: Expansion<'a, 'a>,
+    branch: bool,
+}
+
+impl<'a> ResolveIter<'a> {
+    pub(crate) fn new(search: &'a JsonSearch, context: ResolveContext<'a, 'a>, start: Step<'a, 'a>) -> Self {
+        ResolveIter {
+            search,
+            context,
+            stack: vec![Frame {
+                expansion: Expansion::single(start),
+                branch: false,
+            }],
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Checks if the step of a level can still fail, which happens when it has to produce a step
+    /// that is not a branch, or when the level above it is not a branch and can still fail.
+    fn can_fail(&self, level: usize) -> bool {
+        for (i, frame) in self.stack.iter().enumerate().skip(level) {
+            if frame.expansion.has_required_steps() {
+                return true;
+            }
+
+            if self.stack.get(i + 1).is_none_or(|next| next.branch) {
+                return false;
+            }
+        }
+
+        false
+    }
+
+    /// Checks if a match found at the level can no longer be left out. A failure only leaves out
+    /// the matches of the nearest branch it passes, while a failure that passes no branch fails
+    /// the whole search.
+    fn is_settled(&self, level: usize) -> bool {
+        let levels = self.stack.len().min(level + 1);
+
+        match self.stack[..levels].iter().position(|frame| frame.branch) {
+            Some(branch) => (branch..levels).all(|level| !self.can_fail(level)),
+            None => true,
+        }
+    }
+
+    /// Drops the current level after the step it belongs to failed, together with the matches
+    /// found below it. Returns the error when the failure reaches the start of the search.
+    fn fail(&mut self, error: JsonSearchResolveError) -> Option<JsonSearchResolveError> {
+        while let Some(frame) = self.stack.pop() {
+            let level = self.stack.len();
+
+            while self.pending.back().is_some_and(|(_, found)| *found >= level) {
+                self.pending.pop_back();
+            }
+
+            if frame.branch {
+                return None;
+            }
+        }
+
+        self.pending.clear();
+        Some(error)
+    }
+}
+
+impl Iterator for ResolveIter<'_> {
+    type Item = Result<JsonPath, JsonSearchResolveError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((_, level)) = self.pending.front() {
+                if self.is_settled(*level) {
+                    return self.pending.pop_front().map(|(path, _)| Ok(path));
+                }
+            }
+
+            let frame = self.stack.last_mut()?;
+
+            let step = match frame.expansion.next(self.search, &self.context) {
+                Ok(Some(step)) => step,
+                Ok(None) => {
+                    self.stack.pop();
+
+                    let top = self.stack.len().saturating_sub(1);
+
+                    for (_, level) in self.pending.iter_mut().rev().take_while(|(_, level)| *level > top) {
+                        *level = top;
+                    }
+
+                    continue;
+                }
+                Err(error) => {
+                    self.stack.clear();
+                    self.pending.clear();
+                    return Some(Err(error));
+                }
+            };
+
+            if step.part.is_none() {
+                self.pending.push_back((step.found.into_path(), self.stack.len() - 1));
+                continue;
+            }
+
+            let branch = step.branch;
+
+            match self.search.expand(&self.context, step) {
+                Ok(expansion) => self.stack.push(Frame {
+                    expansion,
+                    branch,
+                }),
+                Err(error) if error.is_function_error() => {
+                    self.stack.clear();
+                    self.pending.clear();
+                    return Some(Err(error));
+                }
+                Err(_) if branch => {}
+                Err(error) => {
+                    if let Some(error) = self.fail(error) {
+                        return Some(Err(error));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use serde_json::json;
+    use crate::json_path::JsonPath;
+    use crate::json_search::{JsonSearch, JsonSearchResolveError};
+    use crate::json_search::filter_function::{FunctionType, FunctionValue};
+    use crate::json_search::function_registry::FunctionRegistry;
+    use crate::json_search::search_syntax::SearchSyntax;
+
+    #[test]
+    fn matches_are_yielded_in_the_order_of_resolve() {
+        let target_value = json!({
+            "a": [{ "id": 1, "tags": ["x"] }, { "id": 2 }, [3, { "id": 4 }]],
+            "b": { "id": 5, "c": { "tags": ["y", "z"] } },
+        });
+
+        for search in ["$.*", "$.**.id", "$.**.*", "$.a[1:]", "$.**.tags.*", "$.a.[0,2].*", "$.a.*.^", "$.**.{k}.id?", "$.**{1,2}", "?.a.*.id"] {
+            let search = JsonSearch::from_str(search).unwrap();
+            let paths: Result<Vec<JsonPath>, JsonSearchResolveError> = search.resolve_iter(&target_value).collect();
+
+            assert_eq!(paths, search.resolve(&target_value), "{}", search);
+        }
+    }
+
+    #[test]
+    fn resolving_can_be_stopped_early() {
+        let visited = Arc::new(AtomicUsize::new(0));
+        let mut functions = FunctionRegistry::new();
+
+        let counter = visited.clone();
+        functions.register("visit", &[FunctionType::Value], FunctionType::Logical, move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            FunctionValue::Logical(true)
+        }).unwrap();
+
+        let target_value = json!({
+            "rows": (0..10_000).map(|i| json!({ "id": i, "cells": [i, i + 1] })).collect::<Vec<_>>(),
+        });
+
+        let search = JsonSearch::parse_with_functions("$.rows[?visit(@.id)].cells.*", SearchSyntax::Native, &functions).unwrap();
+        let mut matches = search.resolve_iter_with_functions(&target_value, &functions);
+
+        assert_eq!(matches.next(), Some(Ok(JsonPath::from(["rows", "0", "cells", "0"]))));
+        assert_eq!(matches.next(), Some(Ok(JsonPath::from(["rows", "0", "cells", "1"]))));
+        assert_eq!(matches.next(), Some(Ok(JsonPath::from(["rows", "1", "cells", "0"]))));
+
+        assert_eq!(visited.load(Ordering::Relaxed), 2);
+        assert!(matches.stack.len() <= 5);
+    }
+
+    #[test]
+    fn failing_branches_yield_nothing_like_with_resolve() {
+        let target_value = json!({
+            "a": { "x": [1, 2, 3, 4, 5, 6], "y": { "z": 1 } },
+            "b": { "x": [1], "y": 2 },
+            "c": [{ "x": [1] }, { "x": [1, 2, 3, 4, 5, 6] }],
+        });
+
+        let searches = [
+            "$.*.x.[0,5]", "$.*.x.[0,5].^", "$.*.y.z", "$.**.x.[0,5]", "$.c.*.x.[0,5]", "$.*.x.[0,5] | @.^",
+            "$.*.*.[0,5]", "$.c[?@.x].x.[0,5]", "$.a.x.[0,9]", "$.*.y.q", "$.c.0.x.[0,5]", "?.*.x.[0,5]",
+        ];
+
+        for search in searches {
+            let search = JsonSearch::from_str(search).unwrap();
+            let paths: Result<Vec<JsonPath>, JsonSearchResolveError> = search.resolve_iter(&target_value).collect();
+
+            assert_eq!(paths, search.resolve(&target_value), "{}", search);
+        }
+
+        let search = JsonSearch::from_str("$.*.x.[0,5]").unwrap();
+
+        assert_eq!(search.resolve_iter(&target_value).collect::<Vec<_>>(), vec![
+            Ok(JsonPath::from(["a", "x", "0"])),
+            Ok(JsonPath::from(["a", "x", "5"])),
+        ]);
+    }
+
+    #[test]
+    fn failures_end_the_iteration() {
+        let target_value = json!({ "a": [1, 2] });
+
+        let search = JsonSearch::from_str("$.a[0,5]").unwrap();
+        let mut matches = search.resolve_iter(&target_value);
+
+        assert_eq!(matches.next(), Some(Ok(JsonPath::from(["a", "0"]))));
+        assert_eq!(matches.next(), Some(Err(JsonSearchResolveError::MissingRequiredIndex(JsonPath::from(["a"]), 5))));
+        assert_eq!(matches.next(), None);
+
+        let search = JsonSearch::from_str("$.b").unwrap();
+        let mut matches = search.resolve_iter(&target_value);
+
+        assert_eq!(matches.next(), Some(Err(JsonSearchResolveError::MissingRequiredKey(JsonPath::default(), "b".to_string()))));
+        assert_eq!(matches.next(), None);
+    }
+}
@@ -0,0 +1,250 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde_json::Value;
+use thiserror::Error;
+use crate::json_path::{JsonPath, JsonPathParseError, JsonPathResolveError};
+use crate::json_search::{JsonSearch, JsonSearchParseError, JsonSearchResolveError};
+use crate::json_search::path_template::PathTemplate;
+use crate::syntax::split_arrow;
+
+/// Rule that moves every match of a search to the path built from a template, written as
+/// `$.users.{id}.name => $.names.{id}`. The placeholders in the template are filled in with the
+/// keys and indices bound by the captures of the same name in the search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RewriteRule {
+    search: JsonSearch,
+    template: PathTemplate,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RewriteRuleParseError {
+    #[error("Rewrite rule should have the form 'search => template'")]
+    MissingArrow,
+
+    #[error("Invalid search in rewrite rule: {0}")]
+    InvalidSearch(JsonSearchParseError),
+
+    #[error("Invalid template in rewrite rule: {0}")]
+    InvalidTemplate(JsonPathParseError),
+
+    #[error("Template uses '{{{0}}}', which is not captured by the search")]
+    UnknownCapture(String),
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RewriteError {
+    #[error("Failed to resolve the search of the rewrite rule: {0}")]
+    FailedToResolve(JsonSearchResolveError),
+
+    #[error("The match at '{0}' did not bind every capture used by the template")]
+    UnboundCapture(JsonPath),
+
+    #[error("Failed to move the value to '{0}': {1}")]
+    FailedToMove(JsonPath, JsonPathResolveError),
+}
+
+impl RewriteRule {
+    /// Creates a rule after checking that every placeholder in the template is captured by the
+    /// search.
+    pub fn new(search: JsonSearch, template: PathTemplate) -> Result<Self, RewriteRuleParseError> {
+        let captures = search.captures();
+
+        if let Some(name) = template.placeholders().into_iter().find(|name| !captures.contains(name)) {
+            return Err(RewriteRuleParseError::UnknownCapture(name.to_string()));
+        }
+
+        Ok(RewriteRule {
+            search,
+            template,
+        })
+    }
+
+    pub fn search(&self) -> &JsonSearch {
+        &self.search
+    }
+
+    pub fn template(&self) -> &PathTemplate {
+        &self.template
+    }
+
+    /// Moves every match to its destination, returning the number of values that were moved.
+    /// Every match is taken out of the document before any of them is stored again, so rules can
+    /// move values into the place of other matches. When several matches end up at the same
+    /// destination, the last one in document order is kept. When the same value is matched more
+    /// than once, it is moved to the destination of the first of those matches. The document is
+    /// left unchanged when any of the values cannot be moved.
+    pub fn apply(&self, target: &mut Value) -> Result<usize, RewriteError> {
+        let matches = self.search.resolve_matches(target)
+            .map_err(RewriteError::FailedToResolve)?;
+
+        let mut moves = vec![];
+
+        for found in matches {
+            let Some(destination) = self.template.render(found.captures()) else {
+                return Err(RewriteError::UnboundCapture(found.into_path()));
+            };
+
+            moves.push((found.into_path(), destination));
+        }
+
+        // Removing later elements of an array first keeps the indices of earlier matches intact.
+        // The sort is stable, so deduplicating keeps the first match of a value that is matched
+        // more than once.
+        moves.sort_by(|(a, _), (b, _)| b.cmp(a));
+        moves.dedup_by(|(a, _), (b, _)| a == b);
+
+        let mut rewritten = target.clone();
+        let mut values = vec![];
+
+        for (source, destination) in moves {
+            // Nothing is left to move when an earlier match already took this value with it.
+            if let Ok(value) = source.remove(&mut rewritten) {
+                values.push((destination, value));
+            }
+        }
+
+        let count = values.len();
+
+        for (destination, value) in values.into_iter().rev() {
+            destination.insert(&mut rewritten, value)
+                .map_err(|error| RewriteError::FailedToMove(destination, error))?;
+        }
+
+        *target = rewritten;
+
+        Ok(count)
+    }
+}
+
+impl FromStr for RewriteRule {
+    type Err = RewriteRuleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((search, template)) = split_arrow(s) else {
+            return Err(RewriteRuleParseError::MissingArrow);
+        };
+
+        let search = search.trim().parse()
+            .map_err(RewriteRuleParseError::InvalidSearch)?;
+
+        let template = template.trim().parse()
+            .map_err(RewriteRuleParseError::InvalidTemplate)?;
+
+        RewriteRule::new(search, template)
+    }
+}
+
+impl Display for RewriteRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} => {}", self.search, self.template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use serde_json::json;
+    use crate::json_path::{JsonPath, JsonPathParseError, JsonPathResolveError};
+    use crate::json_path::path_part::PathPart;
+    use crate::json_search::rewrite_rule::{RewriteError, RewriteRule, RewriteRuleParseError};
+
+    #[test]
+    fn rules_are_parsed_and_validated() {
+        let rule = RewriteRule::from_str("$.users.{id}.name  =>  $.names.{id}").unwrap();
+
+        assert_eq!(rule.to_string(), "$.users.{id}.name => $.names.{id}");
+        assert_eq!(rule.template().placeholders(), vec!["id"]);
+        assert_eq!(RewriteRule::from_str("$.users.*.name"), Err(RewriteRuleParseError::MissingArrow));
+        assert_eq!(RewriteRule::from_str("$.users.{id} => $.names.{user}"), Err(RewriteRuleParseError::UnknownCapture("user".to_string())));
+        assert_eq!(RewriteRule::from_str("$.users.{id} => names.{id}"), Err(RewriteRuleParseError::InvalidTemplate(JsonPathParseError::IncorrectRoot("names".to_string()))));
+        assert!(matches!(RewriteRule::from_str("x.users => $.names"), Err(RewriteRuleParseError::InvalidSearch(_))));
+
+        let rule = RewriteRule::from_str(r#"$["a=>b"].{x} => $.c["=>"].{x}"#).unwrap();
+        assert_eq!(rule.to_string(), r#"$["a=>b"].{x} => $.c["=>"].{x}"#);
+        assert_eq!(RewriteRule::from_str("$./=>/ => $.c").unwrap().to_string(), "$./=>/ => $.c");
+
+        let mut target = json!({ "don't": 1 });
+        assert_eq!(RewriteRule::from_str("$.don't => $.do").unwrap().apply(&mut target), Ok(1));
+        assert_eq!(target, json!({ "do": 1 }));
+    }
+
+    #[test]
+    fn matches_are_moved_to_their_destination() {
+        let mut target = json!({
+            "version": 1,
+            "users": {
+                "u1": { "name": "Ann", "email": "ann@example.test" },
+                "u2": { "name": "Bob" },
+            },
+        });
+
+        assert_eq!(RewriteRule::from_str("$.users.{id}.name => $.names.{id}").unwrap().apply(&mut target), Ok(2));
+        assert_eq!(RewriteRule::from_str("$.users.{id}.email? => $.contacts.{id}.email").unwrap().apply(&mut target), Ok(1));
+        assert_eq!(RewriteRule::from_str("$.version => $.meta.version").unwrap().apply(&mut target), Ok(1));
+
+        assert_eq!(target, json!({
+            "meta": { "version": 1 },
+            "users": { "u1": {}, "u2": {} },
+            "names": { "u1": "Ann", "u2": "Bob" },
+            "contacts": { "u1": { "email": "ann@example.test" } },
+        }));
+    }
+
+    #[test]
+    fn array_elements_are_moved_without_shifting_other_matches() {
+        let mut target = json!({
+            "items": [
+                { "sku": "a", "discontinued": true },
+                { "sku": "b" },
+                { "sku": "c", "discontinued": true },
+            ],
+        });
+
+        let rule = RewriteRule::from_str("$.items[?@.discontinued] => $.archive.{index}");
+        assert_eq!(rule, Err(RewriteRuleParseError::UnknownCapture("index".to_string())));
+
+        let rule = RewriteRule::from_str("$.items.{index}.discontinued.^ => $.archive.{index}").unwrap();
+        assert_eq!(rule.apply(&mut target), Ok(2));
+
+        assert_eq!(target, json!({
+            "items": [{ "sku": "b" }],
+            "archive": [
+                { "sku": "a", "discontinued": true },
+                null,
+                { "sku": "c", "discontinued": true },
+            ],
+        }));
+    }
+
+    #[test]
+    fn values_can_be_moved_into_the_place_of_other_matches() {
+        let mut target = json!({ "pair": { "left": 1, "right": 2 } });
+
+        let rule = RewriteRule::from_str("$.{outer}.{inner} => $.{inner}.{outer}").unwrap();
+        assert_eq!(rule.apply(&mut target), Ok(2));
+        assert_eq!(target, json!({ "pair": {}, "left": { "pair": 1 }, "right": { "pair": 2 } }));
+    }
+
+    #[test]
+    fn failed_moves_return_an_err() {
+        let mut target = json!({ "a": { "b": 1, "d": 3 }, "c": 2 });
+
+        assert_eq!(RewriteRule::from_str("$[a,c,{x}] => $.{x}").unwrap().apply(&mut target), Err(RewriteError::UnboundCapture(JsonPath::from(["a"]))));
+        assert_eq!(target, json!({ "a": { "b": 1, "d": 3 }, "c": 2 }));
+
+        assert_eq!(RewriteRule::from_str("$.a.{x} => $.c.{x}").unwrap().apply(&mut target), Err(RewriteError::FailedToMove(JsonPath::from(["c", "b"]), JsonPathResolveError::FailedToResolvePart(PathPart::Key("b".to_string())))));
+        assert_eq!(target, json!({ "a": { "b": 1, "d": 3 }, "c": 2 }));
+
+        assert_eq!(RewriteRule::from_str("$.c => $.list.999999999").unwrap().apply(&mut target), Err(RewriteError::FailedToMove(JsonPath::from(["list", "999999999"]), JsonPathResolveError::IndexTooFarPastEnd(999999999))));
+        assert_eq!(target, json!({ "a": { "b": 1, "d": 3 }, "c": 2 }));
+    }
+
+    #[test]
+    fn values_matched_more_than_once_are_moved_by_their_first_match() {
+        let mut target = json!({ "a": 1, "b": 2 });
+
+        let rule = RewriteRule::from_str("$.{x}.^.a => $.moved.{x}").unwrap();
+        assert_eq!(rule.apply(&mut target), Ok(1));
+        assert_eq!(target, json!({ "b": 2, "moved": { "a": 1 } }));
+    }
+}
@@ -0,0 +1,550 @@
+use serde_json::Value;
+use crate::json_search::array_slice::ArraySlice;
+use crate::json_search::filter::{Comparable, ComparisonOperator, FilterExpression, FilterQuery};
+use crate::json_search::filter_function::{FunctionCall, FunctionType};
+use crate::json_search::function_registry::FunctionRegistry;
+use crate::json_search::search_part::SearchPart;
+use crate::json_search::{JsonSearch, JsonSearchParseError};
+
+/// Largest magnitude an integer in a query may have, as RFC 9535 limits them to the range that
+/// can be represented exactly by an IEEE 754 double.
+const MAX_INTEGER: i64 = (1 << 53) - 1;
+
+/// Parses a query written in the syntax of RFC 9535. The query is turned into an optional and
+/// lenient search, because the standard returns an empty result for missing or mismatched values
+/// rather than an error.
+pub(crate) fn parse(s: &str, functions: &FunctionRegistry) -> Result<JsonSearch, JsonSearchParseError> {
+    let mut parser = Rfc9535Parser {
+        source: s,
+        position: 0,
+        functions,
+    };
+
+    if !parser.eat('$') {
+        return Err(parser.error("expected '$'"));
+    }
+
+    let parts = parser.parse_segments()?;
+
+    if parser.position < s.len() {
+        return Err(parser.error("unexpected character"));
+    }
+
+    Ok(JsonSearch {
+        parts,
+        optional: true,
+        lenient: true,
+    })
+}
+
+struct Rfc9535Parser<'a> {
+    source: &'a str,
+    position: usize,
+    functions: &'a FunctionRegistry,
+}
+
+impl<'a> Rfc9535Parser<'a> {
+    fn error(&self, reason: &str) -> JsonSearchParseError {
+        JsonSearchParseError::InvalidJsonPath(self.source.to_string(), self.position, reason.to_string())
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += c.len_utf8();
+            return true;
+        }
+
+        false
+    }
+
+    fn eat_str(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.position += token.len();
+            return true;
+        }
+
+        false
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), JsonSearchParseError> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => Err(self.error(&format!("expected '{}'", c))),
+        }
+    }
+
+    /// Skips blank space, which RFC 9535 limits to spaces, tabs, line feeds and carriage returns.
+    fn skip_blank(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn parse_segments(&mut self) -> Result<Vec<SearchPart>, JsonSearchParseError> {
+        let mut parts = vec![];
+
+        loop {
+            let start = self.position;
+            self.skip_blank();
+
+            if self.eat_str("..") {
+                parts.push(SearchPart::RecursiveDescent);
+
+                match self.peek() {
+                    Some('[') => parts.push(self.parse_bracketed_selection()?),
+                    _ => parts.push(self.parse_shorthand()?),
+                }
+            } else if self.eat('.') {
+                parts.push(self.parse_shorthand()?);
+            } else if self.peek() == Some('[') {
+                parts.push(self.parse_bracketed_selection()?);
+            } else {
+                // Blank space is only allowed between segments, so it belongs to whatever comes
+                // after the query.
+                self.position = start;
+                return Ok(parts);
+            }
+        }
+    }
+
+    fn parse_shorthand(&mut self) -> Result<SearchPart, JsonSearchParseError> {
+        if self.eat('*') {
+            return Ok(SearchPart::Wildcard);
+        }
+
+        match self.peek() {
+            Some(c) if is_name_first(c) => {}
+            _ => return Err(self.error("expected a member name or '*'")),
+        }
+
+        let rest = self.rest();
+        let end = rest.find(|c: char| !is_name_first(c) && !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+
+        self.position += end;
+        Ok(SearchPart::Key(rest[..end].to_string()))
+    }
+
+    fn parse_bracketed_selection(&mut self) -> Result<SearchPart, JsonSearchParseError> {
+        self.expect('[')?;
+        let mut selectors = vec![];
+
+        loop {
+            self.skip_blank();
+            selectors.push(self.parse_selector()?);
+            self.skip_blank();
+
+            if self.eat(']') {
+                break;
+            }
+
+            self.expect(',')?;
+        }
+
+        match selectors.len() {
+            1 => Ok(selectors.remove(0)),
+            _ => Ok(SearchPart::Union(selectors)),
+        }
+    }
+
+    fn parse_selector(&mut self) -> Result<SearchPart, JsonSearchParseError> {
+        match self.peek() {
+            Some('\'' | '"') => Ok(SearchPart::Key(self.parse_string()?)),
+            Some('*') => {
+                self.position += 1;
+                Ok(SearchPart::Wildcard)
+            }
+            Some('?') => {
+                self.position += 1;
+                self.skip_blank();
+                Ok(SearchPart::Filter(self.parse_or()?))
+            }
+            _ => self.parse_index_or_slice(),
+        }
+    }
+
+    fn parse_index_or_slice(&mut self) -> Result<SearchPart, JsonSearchParseError> {
+        let start = self.parse_optional_integer()?;
+        self.skip_blank();
+
+        if !self.eat(':') {
+            return match start {
+                Some(index) => Ok(SearchPart::Index(index)),
+                None => Err(self.error("expected a selector")),
+            };
+        }
+
+        self.skip_blank();
+        let end = self.parse_optional_integer()?;
+        self.skip_blank();
+
+        let mut step = None;
+
+        if self.eat(':') {
+            self.skip_blank();
+            step = self.parse_optional_integer()?;
+        }
+
+        Ok(SearchPart::Slice(ArraySlice::new(start, end, step)))
+    }
+
+    fn parse_optional_integer(&mut self) -> Result<Option<isize>, JsonSearchParseError> {
+        match self.peek() {
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_integer().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn parse_integer(&mut self) -> Result<isize, JsonSearchParseError> {
+        let rest = self.rest();
+        let digits_start = usize::from(rest.starts_with('-'));
+        let digits = &rest[digits_start..];
+        let length = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+
+        if length == 0 {
+            return Err(self.error("expected an integer"));
+        }
+
+        if digits.starts_with('0') && (length > 1 || digits_start == 1) {
+            return Err(self.error("integers may not have leading zeros or be negative zero"));
+        }
+
+        let text = &rest[..digits_start + length];
+
+        let value = text.parse::<i64>()
+            .ok()
+            .filter(|value| value.abs() <= MAX_INTEGER)
+            .ok_or_else(|| self.error("integer is out of range"))?;
+
+        self.position += text.len();
+        Ok(value as isize)
+    }
+
+    /// Parses a string literal, which may only contain the escape sequences listed in RFC 9535
+    /// and no unescaped control characters.
+    fn parse_string(&mut self) -> Result<String, JsonSearchParseError> {
+        let quote = self.peek().ok_or_else(|| self.error("expected a string"))?;
+        self.position += 1;
+
+        let mut value = String::new();
+
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+            self.position += c.len_utf8();
+
+            match c {
+                _ if c == quote => return Ok(value),
+                '\\' => {
+                    let escaped = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    self.position += escaped.len_utf8();
+
+                    value.push(match escaped {
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        '/' | '\\' => escaped,
+                        _ if escaped == quote => escaped,
+                        'u' => self.parse_unicode_escape()?,
+                        _ => return Err(self.error("invalid escape sequence")),
+                    });
+                }
+                c if (c as u32) < 0x20 => return Err(self.error("control characters must be escaped")),
+                c => value.push(c),
+            }
+        }
+    }
+
+    fn parse_unicode_escape(&mut self) -> Result<char, JsonSearchParseError> {
+        let high = self.parse_hex()?;
+
+        if (0xDC00..0xE000).contains(&high) {
+            return Err(self.error("unpaired low surrogate"));
+        }
+
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("invalid unicode escape"));
+        }
+
+        if !self.eat_str("\\u") {
+            return Err(self.error("unpaired high surrogate"));
+        }
+
+        let low = self.parse_hex()?;
+
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("unpaired high surrogate"));
+        }
+
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+            .ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn parse_hex(&mut self) -> Result<u32, JsonSearchParseError> {
+        let hex = self.rest().get(..4)
+            .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("expected four hexadecimal digits"))?;
+
+        self.position += 4;
+        Ok(u32::from_str_radix(hex, 16).unwrap_or_default())
+    }
+
+    fn parse_or(&mut self) -> Result<FilterExpression, JsonSearchParseError> {
+        let mut expression = self.parse_and()?;
+
+        loop {
+            let start = self.position;
+            self.skip_blank();
+
+            if !self.eat_str("||") {
+                self.position = start;
+                return Ok(expression);
+            }
+
+            self.skip_blank();
+            expression = FilterExpression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+    }
+
+    fn parse_and(&mut self) -> Result<FilterExpression, JsonSearchParseError> {
+        let mut expression = self.parse_basic()?;
+
+        loop {
+            let start = self.position;
+            self.skip_blank();
+
+            if !self.eat_str("&&") {
+                self.position = start;
+                return Ok(expression);
+            }
+
+            self.skip_blank();
+            expression = FilterExpression::And(Box::new(expression), Box::new(self.parse_basic()?));
+        }
+    }
+
+    fn parse_basic(&mut self) -> Result<FilterExpression, JsonSearchParseError> {
+        if self.eat('!') {
+            self.skip_blank();
+
+            return match self.peek() {
+                Some('(') => Ok(FilterExpression::Not(Box::new(self.parse_parenthesized()?))),
+                _ => {
+                    let operand = self.parse_operand()?;
+                    Ok(FilterExpression::Not(Box::new(self.test(operand)?)))
+                }
+            };
+        }
+
+        if self.peek() == Some('(') {
+            return self.parse_parenthesized();
+        }
+
+        let left = self.parse_operand()?;
+
+        let start = self.position;
+        self.skip_blank();
+
+        let Some(operator) = self.parse_operator() else {
+            self.position = start;
+            return self.test(left);
+        };
+
+        self.skip_blank();
+
+        self.check_comparable(&left)?;
+        let right = self.parse_operand()?;
+        self.check_comparable(&right)?;
+
+        Ok(FilterExpression::Comparison(left, operator, right))
+    }
+
+    /// Turns an operand into a test expression, which is either a query that checks if any value
+    /// exists or a call to a function that returns a logical or nodes.
+    fn test(&self, operand: Comparable) -> Result<FilterExpression, JsonSearchParseError> {
+        match operand {
+            Comparable::Query(query) => Ok(FilterExpression::Exists(query)),
+            Comparable::Function(call) if call.result() != FunctionType::Value => Ok(FilterExpression::Function(call)),
+            Comparable::Function(_) => Err(self.error("the result of the function must be compared to something")),
+            Comparable::Literal(_) | Comparable::Key => Err(self.error("a literal must be compared to something")),
+        }
+    }
+
+    fn parse_parenthesized(&mut self) -> Result<FilterExpression, JsonSearchParseError> {
+        self.expect('(')?;
+        self.skip_blank();
+
+        let expression = self.parse_or()?;
+
+        self.skip_blank();
+        self.expect(')')?;
+
+        Ok(expression)
+    }
+
+    fn parse_operator(&mut self) -> Option<ComparisonOperator> {
+        let operators = [
+            ("==", ComparisonOperator::Equal),
+            ("!=", ComparisonOperator::NotEqual),
+            ("<=", ComparisonOperator::LessOrEqual),
+            (">=", ComparisonOperator::GreaterOrEqual),
+            ("<", ComparisonOperator::Less),
+            (">", ComparisonOperator::Greater),
+        ];
+
+        operators.into_iter()
+            .find(|(token, _)| self.eat_str(token))
+            .map(|(_, operator)| operator)
+    }
+
+    /// Checks that an operand may be used in a comparison, which requires queries to be singular
+    /// and functions to return a value.
+    fn check_comparable(&self, operand: &Comparable) -> Result<(), JsonSearchParseError> {
+        match operand {
+            Comparable::Query(query) if !query.is_singular() => Err(self.error("only singular queries can be compared")),
+            Comparable::Function(call) if call.result() != FunctionType::Value => Err(self.error("the result of the function cannot be compared")),
+            _ => Ok(()),
+        }
+    }
+
+    fn parse_operand(&mut self) -> Result<Comparable, JsonSearchParseError> {
+        match self.peek() {
+            Some('@') => {
+                self.position += 1;
+
+                Ok(Comparable::Query(FilterQuery {
+                    relative: true,
+                    parts: self.parse_segments()?,
+                }))
+            }
+            Some('$') => {
+                self.position += 1;
+
+                Ok(Comparable::Query(FilterQuery {
+                    relative: false,
+                    parts: self.parse_segments()?,
+                }))
+            }
+            Some('\'' | '"') => Ok(Comparable::Literal(Value::String(self.parse_string()?))),
+            Some(c) if c == '-' || c.is_ascii_digit() => Ok(Comparable::Literal(self.parse_number()?)),
+            _ => {
+                for (token, value) in [("true", Value::Bool(true)), ("false", Value::Bool(false)), ("null", Value::Null)] {
+                    if self.eat_str(token) {
+                        return Ok(Comparable::Literal(value));
+                    }
+                }
+
+                match self.peek() {
+                    Some(c) if c.is_ascii_lowercase() => Ok(Comparable::Function(self.parse_function()?)),
+                    _ => Err(self.error("expected a query, a literal or a function call")),
+                }
+            }
+        }
+    }
+
+    fn parse_function(&mut self) -> Result<FunctionCall, JsonSearchParseError> {
+        let rest = self.rest();
+        let end = rest.find(|c: char| !(c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'))
+            .unwrap_or(rest.len());
+
+        let name = &rest[..end];
+        let start = self.position;
+        self.position += end;
+
+        let function = self.functions.get(name)
+            .ok_or_else(|| self.error(&format!("unknown function '{}'", name)))?;
+
+        self.expect('(')?;
+        self.skip_blank();
+
+        let mut arguments = vec![];
+
+        if !self.eat(')') {
+            loop {
+                arguments.push(self.parse_operand()?);
+                self.skip_blank();
+
+                if self.eat(')') {
+                    break;
+                }
+
+                self.expect(',')?;
+                self.skip_blank();
+            }
+        }
+
+        FunctionCall::new(function, arguments)
+            .map_err(|reason| {
+                self.position = start;
+                self.error(&reason)
+            })
+    }
+
+    fn parse_number(&mut self) -> Result<Value, JsonSearchParseError> {
+        let rest = self.rest();
+        let bytes = rest.as_bytes();
+        let mut end = usize::from(rest.starts_with('-'));
+
+        let digits = |from: usize| bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+
+        let integer = digits(end);
+
+        if integer == 0 || (integer > 1 && bytes[end] == b'0') {
+            return Err(self.error("invalid number"));
+        }
+
+        end += integer;
+        let mut is_float = false;
+
+        if bytes.get(end) == Some(&b'.') {
+            let fraction = digits(end + 1);
+
+            if fraction == 0 {
+                return Err(self.error("invalid number"));
+            }
+
+            end += 1 + fraction;
+            is_float = true;
+        }
+
+        if matches!(bytes.get(end), Some(b'e' | b'E')) {
+            end += 1;
+
+            if matches!(bytes.get(end), Some(b'+' | b'-')) {
+                end += 1;
+            }
+
+            let exponent = digits(end);
+
+            if exponent == 0 {
+                return Err(self.error("invalid number"));
+            }
+
+            end += exponent;
+            is_float = true;
+        }
+
+        let text = &rest[..end];
+        self.position += end;
+
+        match text.parse::<i64>() {
+            Ok(value) if !is_float => Ok(Value::from(value)),
+            _ => text.parse::<f64>()
+                .map(Value::from)
+                .map_err(|_| self.error("invalid number")),
+        }
+    }
+}
+
+fn is_name_first(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || !c.is_ascii()
+}
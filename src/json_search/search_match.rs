@@ -0,0 +1,42 @@
+use std::collections::BTreeMap;
+use crate::json_path::JsonPath;
+use crate::json_path::path_part::PathPart;
+
+/// A match of a search together with the keys and indices bound by the captures it passed
+/// through, like `tenant -> "acme"` for `$.tenants.{tenant}.name`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SearchMatch {
+    pub(crate) path: JsonPath,
+    pub(crate) captures: BTreeMap<String, PathPart>,
+}
+
+impl SearchMatch {
+    pub fn path(&self) -> &JsonPath {
+        &self.path
+    }
+
+    pub fn captures(&self) -> &BTreeMap<String, PathPart> {
+        &self.captures
+    }
+
+    /// Returns the key or index bound to the capture with the given name.
+    pub fn capture(&self, name: &str) -> Option<&PathPart> {
+        self.captures.get(name)
+    }
+
+    pub fn into_path(self) -> JsonPath {
+        self.path
+    }
+
+    pub(crate) fn push(&mut self, part: PathPart) {
+        self.path.push(part);
+    }
+
+    /// Returns the match of the value containing this one, keeping the captures made so far.
+    pub(crate) fn parent(&self) -> Option<SearchMatch> {
+        Some(SearchMatch {
+            path: self.path.parent()?,
+            captures: self.captures.clone(),
+        })
+    }
+}
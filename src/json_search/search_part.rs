@@ -1,10 +1,315 @@
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::json_path::element_key::ElementKey;
+use crate::json_search::array_slice::ArraySlice;
+use crate::json_search::depth_range::DepthRange;
+use crate::json_search::filter::FilterExpression;
+use crate::json_search::function_registry::FunctionRegistry;
+use crate::json_search::key_pattern::KeyPattern;
+use crate::json_search::value_type::ValueType;
+use crate::json_search::JsonSearchParseError;
+use crate::syntax::{is_plain_key, parse_quoted, quote, split_top_level};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum SearchPart {
     Key(String),
-    Index(usize),
+    Index(isize),
     Wildcard,
+
+    /// Selects every child like a wildcard, binding the key or index of each child to a name that
+    /// is returned with the matches. Written as `{name}` like `$.tenants.{tenant}.name`.
+    Capture(String),
+
+    RecursiveDescent,
+
+    /// Recursive descent that only visits the given levels below the current value, written as
+    /// `**{1,3}` like `$.**{1,3}.name`. Values deeper than the range are never visited.
+    BoundedDescent(DepthRange),
+
+    Slice(ArraySlice),
+
+    /// Selects every element of an array with a field holding a certain value, written as
+    /// `[id=42]` like `$.users.[id=42].email`. A path with the same part only points at the first
+    /// of these elements.
+    Element(ElementKey),
+
+    Union(Vec<SearchPart>),
+    Filter(FilterExpression),
+    KeyPattern(KeyPattern),
+
+    /// Selects every child of an object except the listed keys, written as `!password` or
+    /// `*!{secret,token}`.
+    Exclusion(Vec<String>),
+
+    /// Only continues with the current value if it is of the given type. Written as a suffix of
+    /// the previous segment like `*:string`, `*{}` or `*[]`.
+    TypeGuard(ValueType),
+
+    /// Moves from the current value back up to the value containing it, written as `^` like
+    /// `$.items.*.sku.^`. A value is returned once for every match it is reached from, and a
+    /// query inside a filter cannot move above the value it starts at.
+    Parent,
+
+    /// Moves to every value containing the current value, from its parent up to the start of the
+    /// search. Written as `^^`.
+    Ancestors,
+
+    /// Moves to every other child of the value containing the current value, in document order.
+    /// Written as `^*`.
+    Siblings,
+
+    /// Marks a single part as optional, written as a `?` suffix like `$.user.nickname?`. Missing
+    /// keys, indices and elements are skipped instead of returning an error, while values of the
+    /// wrong type are only skipped by lenient searches.
+    Optional(Box<SearchPart>),
+}
+
+impl SearchPart {
+    /// Parses a single segment, where filter expressions may call the functions in the given
+    /// registry next to the built-in ones.
+    pub(crate) fn parse_with_functions(s: &str, functions: &FunctionRegistry) -> Result<Self, JsonSearchParseError> {
+        if s == "*" {
+            return Ok(SearchPart::Wildcard);
+        }
+
+        if s == "**" {
+            return Ok(SearchPart::RecursiveDescent);
+        }
+
+        match s {
+            "^" => return Ok(SearchPart::Parent),
+            "^^" => return Ok(SearchPart::Ancestors),
+            "^*" => return Ok(SearchPart::Siblings),
+            _ => {}
+        }
+
+        if let Some(inner) = s.strip_suffix('?').filter(|inner| !inner.is_empty()) {
+            return Ok(SearchPart::Optional(Box::new(SearchPart::parse_with_functions(inner, functions)?)));
+        }
+
+        if let Some(range) = s.strip_prefix("**").filter(|range| range.starts_with('{')) {
+            return Ok(SearchPart::BoundedDescent(range.parse()?));
+        }
+
+        if let Some(excluded) = s.strip_prefix("*!").or_else(|| s.strip_prefix('!')) {
+            return Ok(SearchPart::Exclusion(parse_excluded_keys(excluded)?));
+        }
+
+        if let Some(name) = s.strip_prefix('{').and_then(|s| s.strip_suffix('}')).filter(|name| is_capture_name(name)) {
+            return Ok(SearchPart::Capture(name.to_string()));
+        }
+
+        if let Some(inner) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            return SearchPart::from_bracket(inner, functions);
+        }
+
+        if let Ok(index) = s.parse() {
+            return Ok(SearchPart::Index(index));
+        }
+
+        if s.contains('*') || (s.len() > 1 && s.starts_with('/') && s.ends_with('/')) {
+            return Ok(SearchPart::KeyPattern(s.parse()?));
+        }
+
+        Ok(SearchPart::Key(s.to_string()))
+    }
+
+    /// Parses the contents of a bracketed segment. A single member is returned as is, multiple
+    /// comma separated members are combined into a union.
+    fn from_bracket(s: &str, functions: &FunctionRegistry) -> Result<Self, JsonSearchParseError> {
+        let mut members = split_top_level(s, ',')
+            .into_iter()
+            .map(|member| SearchPart::from_bracket_member(member.trim(), functions))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match members.len() {
+            1 => Ok(members.remove(0)),
+            _ => Ok(SearchPart::Union(members)),
+        }
+    }
+
+    fn from_bracket_member(s: &str, functions: &FunctionRegistry) -> Result<Self, JsonSearchParseError> {
+        if let Some(expression) = s.strip_prefix('?') {
+            return Ok(SearchPart::Filter(FilterExpression::parse_with_functions(expression, functions)?));
+        }
+
+        if let Some(inner) = s.strip_suffix('?').filter(|inner| !inner.is_empty()) {
+            return Ok(SearchPart::Optional(Box::new(SearchPart::from_bracket_member(inner, functions)?)));
+        }
+
+        if let Some(key) = parse_quoted(s) {
+            return Ok(SearchPart::Key(key));
+        }
+
+        if s.starts_with('!') || s.starts_with("*!") {
+            return SearchPart::parse_with_functions(s, functions);
+        }
+
+        if s.contains('=') {
+            return s.parse()
+                .map(SearchPart::Element)
+                .map_err(|_| JsonSearchParseError::InvalidElementKey(s.to_string()));
+        }
+
+        if s.contains(':') {
+            return Ok(SearchPart::Slice(s.parse()?));
+        }
+
+        if s.is_empty() || s == "**" || s.starts_with("**{") {
+            return Err(JsonSearchParseError::InvalidUnionMember(s.to_string()));
+        }
+
+        SearchPart::parse_with_functions(s, functions)
+    }
+
+    /// Adds the names of the captures in this part to the list, including those inside unions and
+    /// optional parts.
+    pub(crate) fn collect_captures<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            SearchPart::Capture(name) => names.push(name),
+            SearchPart::Optional(inner) => inner.collect_captures(names),
+            SearchPart::Union(members) => members.iter().for_each(|member| member.collect_captures(names)),
+            _ => {}
+        }
+    }
+
+    /// Checks if the part only moves down from the value it is applied to, so parents, ancestors
+    /// and siblings are not needed, including inside unions and optional parts.
+    pub(crate) fn moves_down(&self) -> bool {
+        match self {
+            SearchPart::Parent | SearchPart::Ancestors | SearchPart::Siblings => false,
+            SearchPart::Optional(inner) => inner.moves_down(),
+            SearchPart::Union(members) => members.iter().all(SearchPart::moves_down),
+            _ => true,
+        }
+    }
+
+    /// Checks if the filters in this part are self-contained, see
+    /// [FilterExpression::is_self_contained].
+    pub(crate) fn is_self_contained(&self) -> bool {
+        match self {
+            SearchPart::Filter(expression) => expression.is_self_contained(),
+            SearchPart::Optional(inner) => inner.is_self_contained(),
+            SearchPart::Union(members) => members.iter().all(SearchPart::is_self_contained),
+            _ => true,
+        }
+    }
+
+    fn fmt_bracket_member(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchPart::Key(key) if !is_plain_key(key) => write!(f, "{}", quote(key)),
+            SearchPart::Slice(slice) => write!(f, "{}", slice),
+            SearchPart::Element(key) => write!(f, "{}", key),
+            SearchPart::Filter(expression) => write!(f, "?{}", expression),
+            SearchPart::Optional(inner) => {
+                inner.fmt_bracket_member(f)?;
+                write!(f, "?")
+            }
+            part => write!(f, "{}", part),
+        }
+    }
+
+    /// Writes the part as a segment of a search, using bracket notation for keys that cannot be
+    /// written as is.
+    pub(crate) fn fmt_segment(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchPart::Key(key) if !is_plain_key(key) => write!(f, "[{}]", quote(key)),
+            SearchPart::Slice(_) | SearchPart::Element(_) | SearchPart::Union(_) | SearchPart::Filter(_) | SearchPart::TypeGuard(_) => write!(f, "{}", self),
+            SearchPart::Optional(inner) => {
+                inner.fmt_segment(f)?;
+                write!(f, "?")
+            }
+            part => write!(f, ".{}", part),
+        }
+    }
+}
+
+/// Parses the segments of a search or filter query, returning the root segment and the parts that
+/// follow it. Type guard suffixes are split off into separate parts, including those on the root.
+pub(crate) fn parse_segments<'a>(segments: Vec<&'a str>, functions: &FunctionRegistry) -> Result<(&'a str, Vec<SearchPart>), JsonSearchParseError> {
+    let mut segments = segments.into_iter();
+    let mut parts = vec![];
+
+    let (root, guards) = strip_type_guards(segments.next().unwrap_or_default());
+    parts.extend(guards);
+
+    for segment in segments {
+        // Empty keys are written quoted, so an empty segment comes from a stray dot like the one
+        // in `$..id`, which other JSONPath implementations read as a recursive descent.
+        if segment.is_empty() {
+            return Err(JsonSearchParseError::EmptySegment);
+        }
+
+        if segment == "[]" {
+            parts.push(SearchPart::TypeGuard(ValueType::Array));
+            continue;
+        }
+
+        let (base, guards) = strip_type_guards(segment);
+
+        if !base.is_empty() || guards.is_empty() {
+            parts.push(SearchPart::parse_with_functions(base, functions)?);
+        }
+
+        parts.extend(guards);
+    }
+
+    Ok((root, parts))
+}
+
+/// Parses the keys of an exclusion, which is either a single key or a list of keys between
+/// braces. Keys that are not plain should be quoted.
+fn parse_excluded_keys(s: &str) -> Result<Vec<String>, JsonSearchParseError> {
+    let keys = match s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+        Some(inner) => split_top_level(inner, ','),
+        None => vec![s],
+    };
+
+    keys.into_iter()
+        .map(|key| key.trim())
+        .map(|key| match parse_quoted(key) {
+            Some(key) => Some(key),
+            None if !key.is_empty() && !key.contains(['"', '\'', '{', '}', ',']) => Some(key.to_string()),
+            None => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| JsonSearchParseError::InvalidExclusion(s.to_string()))
+}
+
+pub(crate) fn is_capture_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn strip_type_guards(segment: &str) -> (&str, Vec<SearchPart>) {
+    let mut base = segment;
+    let mut guards = vec![];
+
+    loop {
+        if let Some(rest) = base.strip_suffix("{}") {
+            guards.push(SearchPart::TypeGuard(ValueType::Object));
+            base = rest;
+            continue;
+        }
+
+        match base.rsplit_once(':').map(|(rest, name)| (rest, name.parse::<ValueType>())) {
+            Some((rest, Ok(value_type))) => {
+                guards.push(SearchPart::TypeGuard(value_type));
+                base = rest;
+            }
+            _ => break,
+        }
+    }
+
+    guards.reverse();
+    (base, guards)
+}
+
+impl FromStr for SearchPart {
+    type Err = JsonSearchParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SearchPart::parse_with_functions(s, &FunctionRegistry::default())
+    }
 }
 
 impl From<String> for SearchPart {
@@ -27,6 +332,49 @@ impl Display for SearchPart {
             SearchPart::Key(key) => write!(f, "{}", key),
             SearchPart::Index(index) => write!(f, "{}", index),
             SearchPart::Wildcard => write!(f, "*"),
+            SearchPart::Capture(name) => write!(f, "{{{}}}", name),
+            SearchPart::RecursiveDescent => write!(f, "**"),
+            SearchPart::BoundedDescent(range) => write!(f, "**{}", range),
+            SearchPart::Parent => write!(f, "^"),
+            SearchPart::Ancestors => write!(f, "^^"),
+            SearchPart::Siblings => write!(f, "^*"),
+            SearchPart::Slice(slice) => write!(f, "[{}]", slice),
+            SearchPart::Element(key) => write!(f, "[{}]", key),
+            SearchPart::Filter(expression) => write!(f, "[?{}]", expression),
+            SearchPart::KeyPattern(pattern) => write!(f, "{}", pattern),
+            SearchPart::Exclusion(keys) => {
+                // Quoted keys are always written between braces, as quotes are only meaningful
+                // inside a nested part of a segment.
+                if let [key] = keys.as_slice() {
+                    if is_plain_key(key) {
+                        return write!(f, "!{}", key);
+                    }
+                }
+
+                let quoted: Vec<String> = keys.iter()
+                    .map(|key| match is_plain_key(key) {
+                        true => key.to_string(),
+                        false => quote(key),
+                    })
+                    .collect();
+
+                write!(f, "!{{{}}}", quoted.join(","))
+            }
+            SearchPart::TypeGuard(value_type) => write!(f, ":{}", value_type),
+            SearchPart::Optional(inner) => write!(f, "{}?", inner),
+            SearchPart::Union(members) => {
+                write!(f, "[")?;
+
+                for (i, member) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    member.fmt_bracket_member(f)?;
+                }
+
+                write!(f, "]")
+            }
         }
     }
 }
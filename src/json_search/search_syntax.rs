@@ -0,0 +1,13 @@
+/// Grammar used when parsing a [JsonSearch](crate::json_search::JsonSearch) from a string.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SearchSyntax {
+    /// The syntax of this library like `$.users.*.name?`, which is also used by `Display` and
+    /// `Serialize`.
+    #[default]
+    Native,
+
+    /// Strict JSONPath as specified by RFC 9535 like `$.users[*]['name']`. Queries that are not
+    /// well-formed or valid according to the standard are rejected, and the resulting search never
+    /// returns resolve errors.
+    Rfc9535,
+}
@@ -0,0 +1,60 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde_json::Value;
+
+/// The type of a JSON value, used by type guards like `$.labels.*:string` to only continue with
+/// values of that type. The `{}` and `[]` suffixes are short for `:object` and `:array`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    Null,
+    Boolean,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl ValueType {
+    pub fn matches(&self, value: &Value) -> bool {
+        matches!(
+            (self, value),
+            (ValueType::Null, Value::Null)
+                | (ValueType::Boolean, Value::Bool(_))
+                | (ValueType::Number, Value::Number(_))
+                | (ValueType::String, Value::String(_))
+                | (ValueType::Array, Value::Array(_))
+                | (ValueType::Object, Value::Object(_))
+        )
+    }
+}
+
+impl FromStr for ValueType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "null" => Ok(ValueType::Null),
+            "boolean" => Ok(ValueType::Boolean),
+            "number" => Ok(ValueType::Number),
+            "string" => Ok(ValueType::String),
+            "array" => Ok(ValueType::Array),
+            "object" => Ok(ValueType::Object),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for ValueType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ValueType::Null => "null",
+            ValueType::Boolean => "boolean",
+            ValueType::Number => "number",
+            ValueType::String => "string",
+            ValueType::Array => "array",
+            ValueType::Object => "object",
+        };
+
+        write!(f, "{}", name)
+    }
+}
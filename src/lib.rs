@@ -1,2 +1,4 @@
 pub mod json_search;
 pub mod json_path;
+
+mod syntax;
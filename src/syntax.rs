@@ -0,0 +1,194 @@
+/// Splits the string on the given separator, ignoring separators that are nested inside brackets,
+/// parentheses, braces, quoted strings or regular expressions between slashes.
+pub(crate) fn split_top_level(s: &str, separator: char) -> Vec<&str> {
+    split(s, separator, false)
+}
+
+/// Splits a path or search string into its root and segments. Segments are separated by dots,
+/// while a bracketed segment may also directly follow the previous segment, like `$.a["b"][0]`.
+/// Quotes are only meaningful inside brackets, so plain keys may contain them, while a segment
+/// starting with a slash is read up to the closing slash of its regular expression.
+pub(crate) fn split_segments(s: &str) -> Vec<&str> {
+    split(s, '.', true)
+}
+
+/// Splits a search into the stages of a pipe like `$.orders.* | @.lines.*`. Only pipes that are
+/// followed by the `@` of the next stage separate stages, so pipes inside keys and regular
+/// expressions are kept.
+pub(crate) fn split_stages(s: &str) -> Vec<&str> {
+    let mut stages = vec![];
+    let mut start = 0;
+
+    for piece in split_top_level(s, '|').into_iter().skip(1) {
+        let offset = piece.as_ptr() as usize - s.as_ptr() as usize;
+
+        if piece.trim_start().starts_with('@') {
+            stages.push(&s[start..offset - 1]);
+            start = offset;
+        }
+    }
+
+    stages.push(&s[start..]);
+    stages
+}
+
+/// Splits a rewrite rule like `$.users.{id} => $.names.{id}` on its arrow. Arrows inside brackets
+/// and regular expressions are skipped like the dots between segments, so a key like `$["a=>b"]`
+/// can contain one. The last arrow is used when there are several.
+pub(crate) fn split_arrow(s: &str) -> Option<(&str, &str)> {
+    let offset = split(s, '=', true).into_iter()
+        .map(|piece| piece.as_ptr() as usize - s.as_ptr() as usize)
+        .rfind(|offset| s[..*offset].ends_with('=') && s[*offset..].starts_with('>'))?;
+
+    Some((&s[..offset - 1], &s[offset + 1..]))
+}
+
+fn split(s: &str, separator: char, segments_mode: bool) -> Vec<&str> {
+    let mut segments = vec![];
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    // Start of the current segment, as a regular expression can only start a segment. Pieces that
+    // are split on something else than dots can still contain several segments.
+    let mut segment = 0;
+
+    for (i, c) in s.char_indices() {
+        if let Some(q) = quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == q => quote = None,
+                _ => {}
+            }
+
+            continue;
+        }
+
+        match c {
+            '"' | '\'' if depth > 0 || !segments_mode => quote = Some(c),
+            '/' if depth == 0 && s[segment..i].trim().is_empty() => quote = Some(c),
+            '[' if depth == 0 && segments_mode && i > start => {
+                segments.push(&s[start..i]);
+                start = i;
+                segment = i;
+                depth += 1;
+            }
+            '[' | '(' => depth += 1,
+            ']' | ')' if depth > 0 => depth -= 1,
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            _ if depth == 0 && c == separator => {
+                segments.push(&s[start..i]);
+                start = i + c.len_utf8();
+                segment = start;
+            }
+            '.' if depth == 0 => segment = i + 1,
+            _ => {}
+        }
+    }
+
+    segments.push(&s[start..]);
+    segments
+}
+
+/// Parses a single or double quoted string literal at the start of the given string, returning
+/// the unescaped value and the number of bytes consumed.
+pub(crate) fn parse_string_literal(s: &str) -> Option<(String, usize)> {
+    let mut chars = s.char_indices();
+    let (_, quote) = chars.next().filter(|(_, c)| *c == '"' || *c == '\'')?;
+    let mut value = String::new();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            _ if c == quote => return Some((value, i + 1)),
+            '\\' => {
+                let (_, escaped) = chars.next()?;
+
+                value.push(match escaped {
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'u' => {
+                        let mut code = parse_hex(&mut chars)?;
+
+                        if (0xD800..0xDC00).contains(&code) {
+                            let low = match (chars.next()?, chars.next()?) {
+                                ((_, '\\'), (_, 'u')) => parse_hex(&mut chars)?,
+                                _ => return None,
+                            };
+
+                            if !(0xDC00..0xE000).contains(&low) {
+                                return None;
+                            }
+
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                        }
+
+                        char::from_u32(code)?
+                    }
+                    '"' | '\'' | '\\' | '/' => escaped,
+                    _ => return None,
+                });
+            }
+            _ => value.push(c),
+        }
+    }
+
+    None
+}
+
+fn parse_hex(chars: &mut std::str::CharIndices) -> Option<u32> {
+    let hex: String = chars.take(4).map(|(_, c)| c).collect();
+
+    if hex.len() != 4 {
+        return None;
+    }
+
+    u32::from_str_radix(&hex, 16).ok()
+}
+
+/// Parses a string that consists of exactly one quoted string literal.
+pub(crate) fn parse_quoted(s: &str) -> Option<String> {
+    match parse_string_literal(s) {
+        Some((value, length)) if length == s.len() => Some(value),
+        _ => None,
+    }
+}
+
+/// Returns whether a key can be written without quotes and still be parsed back as the same key.
+pub(crate) fn is_plain_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.parse::<isize>().is_err()
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Returns the key as a double quoted string, escaping characters where needed.
+pub(crate) fn quote(key: &str) -> String {
+    serde_json::Value::String(key.to_string()).to_string()
+}
+
+/// Returns the key as a single quoted string, escaped the way RFC 9535 normalized paths are.
+pub(crate) fn quote_normalized(key: &str) -> String {
+    let mut output = String::from("'");
+
+    for c in key.chars() {
+        match c {
+            '\'' => output.push_str("\\'"),
+            '\\' => output.push_str("\\\\"),
+            '\u{8}' => output.push_str("\\b"),
+            '\u{c}' => output.push_str("\\f"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+
+    output.push('\'');
+    output
+}
@@ -0,0 +1,96 @@
+//! Runs the test cases in `tests/fixtures/rfc9535` against the RFC 9535 syntax. Every JSON file in
+//! that directory is expected to use the format of the `cts.json` file of the JSONPath Compliance
+//! Test Suite (https://github.com/jsonpath-standard/jsonpath-compliance-test-suite).
+//!
+//! `cts-subset.json` only holds a hand-picked subset of the official cases. The official suite is
+//! not vendored yet: its `cts.json` should be added unchanged as `tests/fixtures/rfc9535/cts.json`,
+//! with the commit it was taken from noted here, and the tags of any cases that fail because of
+//! missing features listed in [UNSUPPORTED_TAGS] rather than removed from the file. Until then, a
+//! local copy of the official file can be run as well by pointing the `RFC9535_CTS` environment
+//! variable at it.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+use json_search::json_path::path_style::PathStyle;
+use json_search::json_search::JsonSearch;
+use json_search::json_search::search_syntax::SearchSyntax;
+
+/// Tags of test cases that cover parts of the standard that are not supported yet.
+const UNSUPPORTED_TAGS: &[&str] = &[];
+
+fn run_case(case: &Value) -> Result<(), String> {
+    let selector = case["selector"].as_str().ok_or("missing selector")?;
+    let parsed = JsonSearch::parse(selector, SearchSyntax::Rfc9535);
+
+    if case["invalid_selector"].as_bool() == Some(true) {
+        return match parsed {
+            Ok(search) => Err(format!("expected an invalid selector, but got '{}'", search)),
+            Err(_) => Ok(()),
+        };
+    }
+
+    let search = parsed.map_err(|error| error.to_string())?;
+    let document = &case["document"];
+
+    let paths = search.resolve(document)
+        .map_err(|error| error.to_string())?;
+
+    let values = paths.iter()
+        .map(|path| path.resolve(document).cloned().map_err(|error| error.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let expected = match &case["results"] {
+        Value::Array(results) => results.clone(),
+        _ => vec![case["result"].clone()],
+    };
+
+    if !expected.contains(&Value::from(values.clone())) {
+        return Err(format!("expected one of {:?}, but got {:?}", expected, values));
+    }
+
+    if let Some(expected_paths) = case["result_paths"].as_array() {
+        let paths: Vec<Value> = paths.iter()
+            .map(|path| Value::from(path.to_string_with_style(PathStyle::Bracket)))
+            .collect();
+
+        if &paths != expected_paths {
+            return Err(format!("expected paths {:?}, but got {:?}", expected_paths, paths));
+        }
+    }
+
+    Ok(())
+}
+
+fn is_supported(case: &Value) -> bool {
+    case["tags"].as_array()
+        .is_none_or(|tags| tags.iter().all(|tag| !UNSUPPORTED_TAGS.contains(&tag.as_str().unwrap_or_default())))
+}
+
+#[test]
+fn compliance_test_suite_passes() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/rfc9535");
+    let mut failures = vec![];
+    let mut count = 0;
+
+    let suites = fs::read_dir(directory).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().and_then(|extension| extension.to_str()) == Some("json"))
+        .chain(env::var_os("RFC9535_CTS").map(PathBuf::from));
+
+    for path in suites {
+        let suite: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+
+        for case in suite["tests"].as_array().unwrap().iter().filter(|case| is_supported(case)) {
+            count += 1;
+
+            if let Err(reason) = run_case(case) {
+                failures.push(format!("{} ({:?}): {}", case["name"], case["selector"], reason));
+            }
+        }
+    }
+
+    assert!(count > 0, "no compliance test cases were found");
+    assert!(failures.is_empty(), "{} of {} compliance cases failed:\n{}", failures.len(), count, failures.join("\n"));
+}